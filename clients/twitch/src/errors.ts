@@ -0,0 +1,94 @@
+/**
+ * An error response from the Twitch API.
+ *
+ * Every generated method that documents its errors throws a subclass of this
+ * for them, named after the method (e.g., `BanUserError`), whose `S` is the
+ * statuses it documents; `is` narrows on them. Anything else, including a
+ * status the method doesn't document, throws this as-is.
+ */
+export class TwitchError<S extends number = number> extends Error {
+  /**
+   * The status code of the response.
+   */
+  readonly status: number;
+  /**
+   * What the status means for the endpoint, if it is documented.
+   */
+  readonly description: string | undefined;
+  /**
+   * The body of the response; usually `{ error, status, message }`.
+   */
+  readonly body: unknown;
+
+  constructor(status: number, body: unknown, description?: string) {
+    super(errorMessage(status, body, description));
+    this.name = new.target.name;
+    this.status = status;
+    this.description = description;
+    this.body = body;
+  }
+
+  /**
+   * Checks if the error is for the given (documented) status, narrowing its
+   * `status` to it.
+   */
+  is<T extends S>(status: T): this is TwitchError<S> & { status: T } {
+    return this.status === status;
+  }
+}
+
+/**
+ * A class of errors that a request may throw, along with the descriptions of
+ * the statuses it is thrown for.
+ */
+export type TwitchErrorClass = {
+  new (status: number, body: unknown): TwitchError;
+  readonly descriptions?: Readonly<Record<number, string>>;
+};
+
+/**
+ * Creates the error for an error response: an instance of `error`, if it is
+ * thrown for the status, and a plain `TwitchError` otherwise.
+ */
+export function createError(
+  error: TwitchErrorClass,
+  status: number,
+  body: unknown
+): TwitchError {
+  if (error.descriptions && status in error.descriptions) {
+    return new error(status, body);
+  }
+  return new TwitchError(status, body);
+}
+
+/**
+ * Reads the body of an error response, which should be JSON, but may not be.
+ */
+export async function readErrorBody(response: Response): Promise<unknown> {
+  const text = await response.text();
+  try {
+    return JSON.parse(text);
+  } catch {
+    return text;
+  }
+}
+
+function errorMessage(
+  status: number,
+  body: unknown,
+  description: string | undefined
+): string {
+  if (
+    body &&
+    typeof body === "object" &&
+    "message" in body &&
+    typeof body.message === "string" &&
+    body.message
+  ) {
+    return body.message;
+  }
+  if (description) {
+    return `the request failed with status ${status}: ${description}`;
+  }
+  return `the request failed with status ${status}`;
+}
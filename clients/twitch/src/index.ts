@@ -29,10 +29,17 @@ import {
   Subscriptions,
   Tags,
   Teams,
+  UserId,
   Users,
   Videos,
   Whispers,
 } from "./api";
+import {
+  TwitchError,
+  createError,
+  readErrorBody,
+  type TwitchErrorClass,
+} from "./errors";
 import {
   NotificationMessage,
   TokenId,
@@ -40,6 +47,9 @@ import {
   type TokenRequest,
 } from "./types";
 
+export { TwitchError, type TwitchErrorClass } from "./errors";
+export { UserId } from "./api";
+
 export class Twitch {
   static plugin: Discern.Plugin | null = null;
 
@@ -123,30 +133,49 @@ export class Twitch {
     return shot;
   }
 
-  async userId() {
+  /**
+   * The ID of the user the token is for, branded so that it can be passed
+   * wherever the API expects a user (or broadcaster, or moderator) ID.
+   */
+  async userId(): Promise<UserId> {
     const snapshot = await this.snapshot();
-    return snapshot.userId;
+    return UserId.parse(snapshot.userId);
   }
 
+  /**
+   * Sends a request to the API.
+   *
+   * If `type` is given, the response body is parsed with it, unless it is empty
+   * (as with a 204); otherwise, the body is ignored. If `statuses` is given, any
+   * other status, even a successful one, is an error. Error responses are
+   * thrown as an instance of `error`, if it documents their status.
+   */
   async request<Out>(
     url: URL,
     req: RequestInit,
-    type?: ZodType<Out, any, any>
+    type?: ZodType<Out, any, any>,
+    statuses?: readonly number[],
+    error: TwitchErrorClass = TwitchError
   ): Promise<Out> {
     const snapshot = await this.snapshot();
-    req.headers = [
-      ["Client-Id", snapshot.clientId],
-      ["Authorization", `Bearer ${snapshot.accessToken}`],
-    ];
+    // keep any headers the method itself set.
+    const headers = new Headers(req.headers);
+    headers.set("Client-Id", snapshot.clientId);
+    headers.set("Authorization", `Bearer ${snapshot.accessToken}`);
     if (req.body) {
-      req.headers.push(["Content-Type", "application/json"]);
+      headers.set("Content-Type", "application/json");
     }
+    req.headers = headers;
     const response = await fetch(url, req);
     if (response.ok) {
+      await checkStatus(response, statuses);
       if (type) {
-        return type.parse(await response.json());
+        const body = await response.text();
+        if (body) {
+          return type.parse(JSON.parse(body));
+        }
       }
-      return await response.json();
+      return undefined as Out;
     } else if (response.status === 429) {
       // TODO
       const retryAfter = response.headers.get("Retry-After");
@@ -154,27 +183,41 @@ export class Twitch {
         await new Promise((resolve) =>
           setTimeout(resolve, parseInt(retryAfter))
         );
-        return this.request(url, req, type);
+        return this.request(url, req, type, statuses, error);
       } else {
-        // TODO better error
-        throw new Error("Rate limited");
+        throw createError(
+          error,
+          response.status,
+          await readErrorBody(response)
+        );
       }
     } else {
-      throw new Error("bad request");
+      throw createError(
+        error,
+        response.status,
+        await readErrorBody(response)
+      );
     }
   }
 
-  async requestDownload(url: URL, req: RequestInit): Promise<Response> {
+  async requestDownload(
+    url: URL,
+    req: RequestInit,
+    statuses?: readonly number[],
+    error: TwitchErrorClass = TwitchError
+  ): Promise<Response> {
     const snapshot = await this.snapshot();
-    req.headers = [
-      ["Client-Id", snapshot.clientId],
-      ["Authorization", `Bearer ${snapshot.accessToken}`],
-    ];
+    // keep any headers the method itself set.
+    const headers = new Headers(req.headers);
+    headers.set("Client-Id", snapshot.clientId);
+    headers.set("Authorization", `Bearer ${snapshot.accessToken}`);
     if (req.body) {
-      req.headers.push(["Content-Type", "application/json"]);
+      headers.set("Content-Type", "application/json");
     }
+    req.headers = headers;
     const response = await fetch(url, req);
     if (response.ok) {
+      await checkStatus(response, statuses);
       return response;
     } else if (response.status === 429) {
       // TODO
@@ -183,13 +226,20 @@ export class Twitch {
         await new Promise((resolve) =>
           setTimeout(resolve, parseInt(retryAfter))
         );
-        return this.requestDownload(url, req);
+        return this.requestDownload(url, req, statuses, error);
       } else {
-        // TODO better error
-        throw new Error("Rate limited");
+        throw createError(
+          error,
+          response.status,
+          await readErrorBody(response)
+        );
       }
     } else {
-      throw new Error("bad request");
+      throw createError(
+        error,
+        response.status,
+        await readErrorBody(response)
+      );
     }
   }
 
@@ -282,6 +332,21 @@ export class Twitch {
   }
 }
 
+/**
+ * Checks that a successful response has one of the statuses the method
+ * documents; an undocumented one is thrown as a `TwitchError`, like any other
+ * unexpected response.
+ */
+async function checkStatus(response: Response, statuses?: readonly number[]) {
+  if (statuses !== undefined && !statuses.includes(response.status)) {
+    throw new TwitchError(
+      response.status,
+      await readErrorBody(response),
+      `expected a ${statuses.join(" or ")} response`
+    );
+  }
+}
+
 async function snapshot(tokenId: TokenId) {
   if (!Twitch.plugin) {
     throw new Error("Twitch plugin not loaded?");
@@ -0,0 +1,104 @@
+//! Compares the rendered client against what is already in the output
+//! directory, so that CI can catch hand-edits to generated files, and
+//! generated files that were never regenerated.
+
+use crate::render::Files;
+use anyhow::Context as _;
+use std::path::Path;
+
+/// A single file that does not match what we would generate.
+#[derive(Debug)]
+pub struct Drift {
+    /// The file name, relative to the output directory.
+    pub name: String,
+    pub kind: DriftKind,
+    /// A unified diff from what is on disk to what we would generate.
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriftKind {
+    /// The file exists, but its contents differ.
+    Stale,
+    /// We would generate the file, but it does not exist.
+    Missing,
+    /// The file was generated by us, but we would no longer generate
+    /// it.
+    Extraneous,
+}
+
+impl std::fmt::Display for DriftKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DriftKind::Stale => f.write_str("stale"),
+            DriftKind::Missing => f.write_str("missing"),
+            DriftKind::Extraneous => f.write_str("extraneous"),
+        }
+    }
+}
+
+/// Checks the rendered files against the output directory.
+///
+/// Nothing is written.  Files in the output directory that we would
+/// not generate are only reported if we own them (see
+/// [`crate::manifest::owned_files`]); hand-written files are left
+/// alone.
+pub fn check(files: &Files, dir: &Path) -> Result<Vec<Drift>, anyhow::Error> {
+    let mut drift = Vec::new();
+
+    for (name, expected) in files {
+        let path = dir.join(name);
+        let actual = match std::fs::read(&path) {
+            Ok(actual) => Some(actual),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(e).with_context(|| format!("when reading {}", path.display()));
+            }
+        };
+
+        match actual {
+            Some(actual) if actual == *expected => {}
+            Some(actual) => drift.push(Drift {
+                name: name.clone(),
+                kind: DriftKind::Stale,
+                diff: diff(name, &actual, expected),
+            }),
+            None => drift.push(Drift {
+                name: name.clone(),
+                kind: DriftKind::Missing,
+                diff: diff(name, b"", expected),
+            }),
+        }
+    }
+
+    let mut extraneous = Vec::new();
+    for name in crate::manifest::owned_files(dir)? {
+        if files.contains_key(&name) {
+            continue;
+        }
+        let path = dir.join(&name);
+        let actual = match std::fs::read(&path) {
+            Ok(actual) => actual,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("when reading {}", path.display())),
+        };
+        extraneous.push(Drift {
+            diff: diff(&name, &actual, b""),
+            name,
+            kind: DriftKind::Extraneous,
+        });
+    }
+    extraneous.sort_by(|a, b| a.name.cmp(&b.name));
+    drift.extend(extraneous);
+
+    Ok(drift)
+}
+
+fn diff(name: &str, old: &[u8], new: &[u8]) -> String {
+    let old = String::from_utf8_lossy(old);
+    let new = String::from_utf8_lossy(new);
+    similar::TextDiff::from_lines(&old, &new)
+        .unified_diff()
+        .header(&format!("a/{name}"), &format!("b/{name}"))
+        .to_string()
+}
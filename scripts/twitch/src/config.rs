@@ -0,0 +1,575 @@
+//! The project configuration, `twitch-gen.toml`.
+//!
+//! Every knob the generator has lives here, with defaults matching
+//! what the generator did before it had a configuration file; so, a
+//! missing configuration file (or a missing section of one) behaves
+//! exactly as it always has.  The file is discovered by walking up
+//! from the working directory, the same way cargo finds its
+//! manifest, and relative paths in it are relative to the directory
+//! it is in.
+
+use crate::filter::Filters;
+use crate::scopes::ScopeOverrides;
+use anyhow::Context as _;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// The name of the configuration file.
+pub const CONFIG_FILE: &str = "twitch-gen.toml";
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub spec: SpecConfig,
+    pub render: RenderConfig,
+    pub token_params: TokenParamConfig,
+    /// The kinds of IDs that are given a type of their own.
+    pub ids: Vec<IdType>,
+    /// Filters applied to every target.
+    pub filters: Filters,
+    pub targets: Vec<Target>,
+    /// Overrides for individual operations, keyed by operationId.
+    pub operations: BTreeMap<String, OperationOverride>,
+    /// Overrides for the named schemas, keyed by their name in the
+    /// spec.
+    pub schemas: BTreeMap<String, SchemaOverride>,
+    /// The corrections in the scopes file, `spec.scopes`; loaded
+    /// along with the configuration.
+    #[serde(skip)]
+    pub scopes: ScopeOverrides,
+}
+
+/// Where the spec, and everything we apply to it, lives.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SpecConfig {
+    pub path: PathBuf,
+    pub source: String,
+    pub patches: PathBuf,
+    pub overlays: PathBuf,
+    /// The corrections to the scopes the spec says operations need;
+    /// see [`crate::scopes`].
+    pub scopes: PathBuf,
+    pub lock: PathBuf,
+    pub cache: PathBuf,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RenderConfig {
+    /// The width that doc comments are wrapped to, not including the
+    /// comment markers or indentation.
+    pub comment_width: usize,
+    /// The imports at the top of every generated file.
+    pub imports: Vec<String>,
+    /// The prefix for the names of the shared types, generated from
+    /// the named schemas in the spec; e.g., `User` becomes
+    /// `TwitchUser`.
+    pub type_prefix: String,
+    /// The hand-written module with `TwitchError` in it, as imported
+    /// from the generated files.
+    pub errors_module: String,
+}
+
+/// How we decide which query and body parameters are user IDs.
+///
+/// Every parameter is checked against the rules, in order, and the
+/// first rule that matches it decides its role; a parameter that no
+/// rule matches is a plain parameter.  `operations.<id>.token-params`
+/// overrides the rules for the parameters of a single operation.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TokenParamConfig {
+    pub rules: Vec<TokenParamRule>,
+}
+
+/// A rule that gives a role to the parameters it matches.  A
+/// parameter matches if it meets every condition the rule has.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TokenParamRule {
+    /// The name of the rule, as reported by `lint --explain`.
+    pub name: String,
+    pub role: TokenRole,
+    /// Where the parameter is sent; either, if not given.
+    #[serde(default, rename = "in")]
+    pub location: Option<TokenParamLocation>,
+    /// The names the parameter may have; any, if empty.
+    #[serde(default)]
+    pub names: Vec<String>,
+    /// A suffix the name of the parameter must have.
+    #[serde(default)]
+    pub name_suffix: Option<String>,
+    /// Phrases, one of which the description of the parameter must
+    /// contain; any description, if empty.
+    #[serde(default)]
+    pub description_contains: Vec<String>,
+}
+
+/// What a parameter is, as far as the token is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenRole {
+    /// The ID of the user the token belongs to; optional in the
+    /// request, and filled in for the caller if they leave it out.
+    /// Only ever a string.
+    CurrentUser,
+    /// The ID of some user, typed as such.  Only ever a string, or an
+    /// array of them.
+    User,
+    /// Nothing to do with the token.
+    Plain,
+}
+
+impl std::fmt::Display for TokenRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenRole::CurrentUser => f.write_str("current-user"),
+            TokenRole::User => f.write_str("user"),
+            TokenRole::Plain => f.write_str("plain"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenParamLocation {
+    Query,
+    Body,
+}
+
+/// A kind of ID, such as a user ID, that is given a branded type of
+/// its own; so that, say, a game ID can't be passed where a user ID
+/// is expected.
+///
+/// Fields and parameters are matched by their name on the wire.
+/// Strings (and arrays of them) that match are typed as the ID;
+/// anything else that matches is left alone.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct IdType {
+    /// The name of the generated type; e.g., `UserId`.
+    pub name: String,
+    pub description: Option<String>,
+    /// The names of the fields and parameters that are this kind of
+    /// ID.
+    pub fields: Vec<String>,
+}
+
+/// A directory to generate a client into.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Target {
+    pub name: String,
+    pub out: PathBuf,
+    /// Filters applied to this target only, on top of the global
+    /// filters.
+    #[serde(default)]
+    pub filters: Filters,
+}
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct OperationOverride {
+    /// Skips the operation entirely.
+    pub skip: bool,
+    /// The name of the generated method, instead of the camel-cased
+    /// operationId.
+    pub name: Option<String>,
+    /// The namespace to generate the method in, instead of the one
+    /// named by the operation's first tag.
+    pub namespace: Option<String>,
+    /// The description of the generated method.
+    pub description: Option<String>,
+    /// The roles of the query and body parameters, by their name in
+    /// the request, instead of what `token-params.rules` decides.
+    pub token_params: BTreeMap<String, TokenRole>,
+    /// Fields that can be `null`, though the spec doesn't say so.
+    /// The first part of each path is either a parameter (by its name
+    /// in the request), or `response`, for the response body.
+    pub nullable: Vec<FieldPath>,
+}
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SchemaOverride {
+    /// Fields of the schema that can be `null`, though the spec
+    /// doesn't say so; including where the schema is inlined, as a
+    /// request or response body.
+    pub nullable: Vec<FieldPath>,
+}
+
+/// A path to a field, by the names the fields have on the wire, with
+/// `[]` for the items of an array; e.g., `data[].ended_at`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub struct FieldPath {
+    raw: String,
+    segments: Vec<FieldSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldSegment {
+    Field(String),
+    Items,
+}
+
+impl FieldPath {
+    pub fn segments(&self) -> &[FieldSegment] { &self.segments }
+}
+
+impl TryFrom<String> for FieldPath {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        let mut segments = Vec::new();
+        for part in raw.split('.') {
+            let mut field = part;
+            let mut items = 0;
+            while let Some(rest) = field.strip_suffix("[]") {
+                field = rest;
+                items += 1;
+            }
+            if field.is_empty() || field.contains(['[', ']']) {
+                anyhow::bail!("`{raw}` is not a valid field path (e.g., `data[].ended_at`)");
+            }
+            segments.push(FieldSegment::Field(field.to_string()));
+            segments.extend(std::iter::repeat_n(FieldSegment::Items, items));
+        }
+        Ok(Self { raw, segments })
+    }
+}
+
+impl std::fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str(&self.raw) }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            spec: SpecConfig::default(),
+            render: RenderConfig::default(),
+            token_params: TokenParamConfig::default(),
+            ids: default_ids(),
+            filters: Filters {
+                exclude: crate::filter::FilterRules {
+                    namespaces: vec!["eventSub".to_string()],
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            targets: vec![Target {
+                name: "client".to_string(),
+                out: PathBuf::from("./clients/twitch/src/api"),
+                filters: Filters::default(),
+            }],
+            operations: BTreeMap::new(),
+            schemas: BTreeMap::new(),
+            scopes: ScopeOverrides::default(),
+        }
+    }
+}
+
+impl Default for SpecConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("./openapi.json"),
+            source: crate::file::RAW_JSON_FILE.to_string(),
+            patches: PathBuf::from(crate::file::DEFAULT_PATCH_DIR),
+            overlays: PathBuf::from(crate::overlay::DEFAULT_OVERLAY_DIR),
+            scopes: PathBuf::from(crate::scopes::DEFAULT_SCOPES_FILE),
+            lock: PathBuf::from(crate::file::DEFAULT_LOCK_FILE),
+            cache: PathBuf::from(crate::file::DEFAULT_CACHE_DIR),
+        }
+    }
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            comment_width: 74,
+            imports: vec![
+                "import type { Twitch } from \"../index\";".to_string(),
+                "import { z } from \"zod\";".to_string(),
+            ],
+            type_prefix: "Twitch".to_string(),
+            errors_module: "../errors".to_string(),
+        }
+    }
+}
+
+impl Default for TokenParamConfig {
+    fn default() -> Self {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Self {
+            rules: vec![
+                TokenParamRule {
+                    name: "current-user".to_string(),
+                    role: TokenRole::CurrentUser,
+                    location: None,
+                    names: Vec::new(),
+                    name_suffix: Some("_id".to_string()),
+                    description_contains: names(&[
+                        "the user ID in the access token",
+                        "the user ID in the user access token",
+                    ]),
+                },
+                TokenParamRule {
+                    name: "query-user-ids".to_string(),
+                    role: TokenRole::User,
+                    location: Some(TokenParamLocation::Query),
+                    names: names(&["user_id", "broadcaster_id"]),
+                    name_suffix: None,
+                    description_contains: Vec::new(),
+                },
+                TokenParamRule {
+                    name: "body-user-ids".to_string(),
+                    role: TokenRole::User,
+                    location: Some(TokenParamLocation::Body),
+                    names: names(&["user_id", "broadcaster_id", "moderator_id"]),
+                    name_suffix: None,
+                    description_contains: Vec::new(),
+                },
+            ],
+        }
+    }
+}
+
+fn default_ids() -> Vec<IdType> {
+    let id = |name: &str, description: &str, fields: &[&str]| IdType {
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        fields: fields.iter().map(|field| field.to_string()).collect(),
+    };
+    vec![
+        id(
+            "UserId",
+            "The ID of a user; a broadcaster and a moderator are users, too.",
+            &[
+                "user_id",
+                "broadcaster_id",
+                "moderator_id",
+                "from_broadcaster_id",
+                "to_broadcaster_id",
+                "from_user_id",
+                "to_user_id",
+                "target_user_id",
+                "creator_id",
+                "gifter_id",
+            ],
+        ),
+        id("GameId", "The ID of a game, or category.", &["game_id"]),
+        id("ClipId", "The ID of a clip.", &["clip_id"]),
+        id("VideoId", "The ID of a video.", &["video_id"]),
+    ]
+}
+
+impl TokenParamRule {
+    /// Whether the rule matches the parameter.  This doesn't check
+    /// that the parameter's type suits the role.
+    pub fn matches(
+        &self,
+        location: TokenParamLocation,
+        name: &str,
+        description: Option<&str>,
+    ) -> bool {
+        self.location.is_none_or(|expected| expected == location)
+            && (self.names.is_empty() || self.names.iter().any(|n| n == name))
+            && self
+                .name_suffix
+                .as_deref()
+                .is_none_or(|suffix| name.ends_with(suffix))
+            && (self.description_contains.is_empty()
+                || description.is_some_and(|description| {
+                    self.description_contains
+                        .iter()
+                        .any(|phrase| description.contains(phrase.as_str()))
+                }))
+    }
+}
+
+impl Config {
+    /// Loads the configuration.
+    ///
+    /// If `path` is given, that file must exist.  Otherwise, the
+    /// configuration file is searched for in the working directory
+    /// and its ancestors; if there isn't one, the defaults are used.
+    /// Either way, the scopes file is loaded along with it.
+    pub fn load(path: Option<&Path>) -> Result<Self, anyhow::Error> {
+        let path = match path {
+            Some(path) => Some(path.to_path_buf()),
+            None => discover()?,
+        };
+        let Some(path) = path else {
+            let mut config = Self::default();
+            config.scopes = ScopeOverrides::load(&config.spec.scopes)?;
+            return Ok(config);
+        };
+
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("when reading the config file {}", path.display()))?;
+        let mut config: Self = toml::from_str(&contents)
+            .with_context(|| format!("when parsing the config file {}", path.display()))?;
+        config
+            .validate()
+            .with_context(|| format!("in the config file {}", path.display()))?;
+
+        if let Some(base) = path.parent() {
+            config.resolve_paths(base);
+        }
+        config.scopes = ScopeOverrides::load(&config.spec.scopes)?;
+        Ok(config)
+    }
+
+    /// The kind of ID that the field or parameter, by its name on the
+    /// wire, is; if it is one.
+    pub fn id_type(&self, field: &str) -> Option<&IdType> {
+        self.ids
+            .iter()
+            .find(|id| id.fields.iter().any(|name| name == field))
+    }
+
+    /// Retrieves the target with the given name.
+    pub fn target(&self, name: &str) -> Result<&Target, anyhow::Error> {
+        self.targets
+            .iter()
+            .find(|target| target.name == name)
+            .with_context(|| {
+                format!(
+                    "there is no target named `{name}`; the targets are: {}",
+                    self.targets
+                        .iter()
+                        .map(|target| target.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        if !(20..=200).contains(&self.render.comment_width) {
+            anyhow::bail!(
+                "`render.comment-width` must be between 20 and 200, but is {}",
+                self.render.comment_width
+            );
+        }
+
+        if !self.render.type_prefix.is_empty() && !is_identifier(&self.render.type_prefix) {
+            anyhow::bail!(
+                "`render.type-prefix` must be a valid identifier, but is `{}`",
+                self.render.type_prefix
+            );
+        }
+
+        if self.targets.is_empty() {
+            anyhow::bail!("there must be at least one `[[targets]]` entry");
+        }
+        let mut names = HashSet::new();
+        let mut outs = HashSet::new();
+        for target in &self.targets {
+            if !names.insert(&target.name) {
+                anyhow::bail!("there is more than one target named `{}`", target.name);
+            }
+            if !outs.insert(&target.out) {
+                anyhow::bail!(
+                    "there is more than one target writing to `{}`",
+                    target.out.display()
+                );
+            }
+            target
+                .filters
+                .validate()
+                .with_context(|| format!("in the filters for the target `{}`", target.name))?;
+        }
+        self.filters.validate().context("in `filters`")?;
+
+        let mut rules = HashSet::new();
+        for rule in &self.token_params.rules {
+            if rule.name.is_empty() {
+                anyhow::bail!("every `token-params.rules` entry must have a name");
+            }
+            if !rules.insert(&rule.name) {
+                anyhow::bail!(
+                    "there is more than one `token-params.rules` entry named `{}`",
+                    rule.name
+                );
+            }
+        }
+
+        let mut ids = HashSet::new();
+        let mut fields = BTreeMap::new();
+        for id in &self.ids {
+            if !is_identifier(&id.name) {
+                anyhow::bail!(
+                    "every `ids` entry must have a name that is a valid identifier, but one is \
+                     `{}`",
+                    id.name
+                );
+            }
+            if !ids.insert(&id.name) {
+                anyhow::bail!("there is more than one `ids` entry named `{}`", id.name);
+            }
+            for field in &id.fields {
+                if let Some(other) = fields.insert(field, &id.name) {
+                    anyhow::bail!(
+                        "the field `{field}` is in both the `{other}` and the `{}` `ids` entries",
+                        id.name
+                    );
+                }
+            }
+        }
+
+        for (id, op) in &self.operations {
+            if let Some(name) = op.name.as_deref() {
+                if !is_identifier(name) {
+                    anyhow::bail!(
+                        "`operations.{id}.name` must be a valid identifier, but is `{name}`"
+                    );
+                }
+            }
+            if let Some(namespace) = op.namespace.as_deref() {
+                if !is_identifier(namespace) {
+                    anyhow::bail!(
+                        "`operations.{id}.namespace` must be a valid identifier, but is \
+                         `{namespace}`"
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_paths(&mut self, base: &Path) {
+        let resolve = |path: &mut PathBuf| {
+            if path.is_relative() && path.as_os_str() != "-" {
+                *path = base.join(&*path);
+            }
+        };
+        resolve(&mut self.spec.path);
+        resolve(&mut self.spec.patches);
+        resolve(&mut self.spec.overlays);
+        resolve(&mut self.spec.scopes);
+        resolve(&mut self.spec.lock);
+        resolve(&mut self.spec.cache);
+        for target in &mut self.targets {
+            resolve(&mut target.out);
+        }
+    }
+}
+
+fn discover() -> Result<Option<PathBuf>, anyhow::Error> {
+    let cwd = std::env::current_dir().context("when finding the working directory")?;
+    Ok(cwd
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|path| path.is_file()))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
@@ -0,0 +1,594 @@
+//! Compares the API generated from two versions of the spec, so that
+//! we know how the generated client changes before we publish it.
+//!
+//! Changes are classified from the point of view of someone using the
+//! generated client: anything that could make code that compiled
+//! against the old client stop compiling (or stop working) is
+//! breaking.  For requests, that means anything that narrows what may
+//! be passed in; for responses, anything that widens what may come
+//! back, or takes away something that used to be there.
+
+use crate::ir::{AdditionalProperties, Api, Method, Param, Schema, Type};
+use indexmap::IndexMap;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A single difference between the two versions of the API.
+#[derive(Debug, serde::Serialize)]
+pub struct Change {
+    /// Where the change is, e.g. `moderation.banUser`, or
+    /// `users.getUsers.response.data[].login`.
+    pub location: String,
+    pub kind: ChangeKind,
+    pub breaking: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    NamespaceAdded,
+    NamespaceRemoved,
+    MethodAdded,
+    MethodRemoved,
+    ParamAdded,
+    ParamRemoved,
+    ParamRequired,
+    ParamOptional,
+    FieldAdded,
+    FieldRemoved,
+    FieldRequired,
+    FieldOptional,
+    TypeChanged,
+    EnumVariantAdded,
+    EnumVariantRemoved,
+    NullableAdded,
+    NullableRemoved,
+    AdditionalPropertiesAdded,
+    AdditionalPropertiesRemoved,
+    StatusAdded,
+    StatusRemoved,
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// The version bump that a set of changes calls for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Bump {
+    None,
+    Minor,
+    Major,
+}
+
+impl std::fmt::Display for Bump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Bump::None => f.write_str("none"),
+            Bump::Minor => f.write_str("minor"),
+            Bump::Major => f.write_str("major"),
+        }
+    }
+}
+
+/// Determines the version bump needed for the given changes.
+pub fn bump(changes: &[Change]) -> Bump {
+    if changes.iter().any(|change| change.breaking) {
+        Bump::Major
+    } else if changes.is_empty() {
+        Bump::None
+    } else {
+        Bump::Minor
+    }
+}
+
+/// Compares two extracted APIs.  Changes are returned in namespace,
+/// then method, order.
+pub fn diff(old: &Api, new: &Api) -> Vec<Change> {
+    let mut diff = Diff {
+        old_schemas: &old.schemas,
+        new_schemas: &new.schemas,
+        changes: Vec::new(),
+    };
+
+    let names = old
+        .namespaces
+        .keys()
+        .chain(new.namespaces.keys())
+        .collect::<BTreeSet<_>>();
+    for name in names {
+        match (old.namespaces.get(name), new.namespaces.get(name)) {
+            (Some(_), None) => diff.push(
+                name.clone(),
+                ChangeKind::NamespaceRemoved,
+                true,
+                "namespace removed".to_string(),
+            ),
+            (None, Some(_)) => diff.push(
+                name.clone(),
+                ChangeKind::NamespaceAdded,
+                false,
+                "namespace added".to_string(),
+            ),
+            (Some(old), Some(new)) => {
+                let old = by_name(&old.methods);
+                let new = by_name(&new.methods);
+                let methods = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+                for method in methods {
+                    let location = format!("{name}.{method}");
+                    match (old.get(method), new.get(method)) {
+                        (Some(_), None) => diff.push(
+                            location,
+                            ChangeKind::MethodRemoved,
+                            true,
+                            "method removed".to_string(),
+                        ),
+                        (None, Some(_)) => diff.push(
+                            location,
+                            ChangeKind::MethodAdded,
+                            false,
+                            "method added".to_string(),
+                        ),
+                        (Some(old), Some(new)) => diff.method(&location, old, new),
+                        (None, None) => unreachable!(),
+                    }
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+
+    diff.changes
+}
+
+fn by_name(methods: &[Method]) -> BTreeMap<&str, &Method> {
+    methods
+        .iter()
+        .map(|method| (method.name.as_str(), method))
+        .collect()
+}
+
+/// Which way data flows through a type.  This decides whether
+/// widening the type is breaking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Request,
+    Response,
+}
+
+/// The shape of a type, as far as the generated client can tell.
+/// Anything the client does not distinguish (e.g. integers and
+/// numbers) is not distinguished here, either.
+#[derive(Debug, PartialEq, Eq)]
+enum Shape {
+    String(BTreeSet<String>),
+    Number,
+    Boolean,
+    /// An ID of the named kind.
+    Id(String),
+    Array(Box<Shape>),
+    Object {
+        /// The fields, and whether each is optional.
+        fields: BTreeMap<String, (Shape, bool)>,
+        /// The fields other than those, if there may be any.
+        additional: Option<Box<Shape>>,
+    },
+    Union(Vec<Shape>),
+    Intersection(Vec<Shape>),
+    Nullable(Box<Shape>),
+    /// A schema, referenced from inside itself.  Its contents are
+    /// compared where it is first expanded.
+    Recursive(String),
+    Unknown,
+}
+
+impl Shape {
+    fn describe(&self) -> String {
+        match self {
+            Shape::String(variants) if variants.is_empty() => "string".to_string(),
+            Shape::String(_) => "enum".to_string(),
+            Shape::Number => "number".to_string(),
+            Shape::Boolean => "boolean".to_string(),
+            Shape::Id(name) => name.clone(),
+            Shape::Array(item) => format!("{}[]", item.describe()),
+            Shape::Object { .. } => "object".to_string(),
+            Shape::Union(_) => "union".to_string(),
+            Shape::Intersection(_) => "intersection".to_string(),
+            Shape::Nullable(inner) => format!("{} | null", inner.describe()),
+            Shape::Recursive(name) => name.clone(),
+            Shape::Unknown => "unknown".to_string(),
+        }
+    }
+}
+
+struct Diff<'a> {
+    old_schemas: &'a Schemas,
+    new_schemas: &'a Schemas,
+    changes: Vec<Change>,
+}
+
+impl Diff<'_> {
+    fn push(&mut self, location: String, kind: ChangeKind, breaking: bool, message: String) {
+        self.changes.push(Change {
+            location,
+            kind,
+            breaking,
+            message,
+        });
+    }
+
+    fn method(&mut self, location: &str, old: &Method, new: &Method) {
+        // the client throws for any status it wasn't told to expect.
+        for status in &old.statuses {
+            if !new.statuses.contains(status) {
+                self.push(
+                    location.to_string(),
+                    ChangeKind::StatusRemoved,
+                    true,
+                    format!("a {status} response is no longer expected"),
+                );
+            }
+        }
+        for status in &new.statuses {
+            if !old.statuses.contains(status) {
+                self.push(
+                    location.to_string(),
+                    ChangeKind::StatusAdded,
+                    false,
+                    format!("a {status} response is now expected"),
+                );
+            }
+        }
+
+        let old_params = params(old);
+        let new_params = params(new);
+        let names = old_params
+            .keys()
+            .chain(new_params.keys())
+            .collect::<BTreeSet<_>>();
+        for name in names {
+            let location = format!("{location}({name})");
+            match (old_params.get(name), new_params.get(name)) {
+                (Some(_), None) => self.push(
+                    location,
+                    ChangeKind::ParamRemoved,
+                    true,
+                    "parameter removed".to_string(),
+                ),
+                (None, Some(param)) => self.push(
+                    location,
+                    ChangeKind::ParamAdded,
+                    !param.is_optional(),
+                    if param.is_optional() {
+                        "optional parameter added".to_string()
+                    } else {
+                        "required parameter added".to_string()
+                    },
+                ),
+                (Some(old), Some(new)) => {
+                    if old.is_optional() && !new.is_optional() {
+                        self.push(
+                            location.clone(),
+                            ChangeKind::ParamRequired,
+                            true,
+                            "parameter is now required".to_string(),
+                        );
+                    } else if !old.is_optional() && new.is_optional() {
+                        self.push(
+                            location.clone(),
+                            ChangeKind::ParamOptional,
+                            false,
+                            "parameter is now optional".to_string(),
+                        );
+                    }
+                    let old = param_shape(old, self.old_schemas);
+                    let new = param_shape(new, self.new_schemas);
+                    self.shape(&location, &old, &new, Direction::Request);
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+
+        let old_ret = old
+            .response
+            .as_ref()
+            .map(|ret| type_shape(ret, self.old_schemas));
+        let new_ret = new
+            .response
+            .as_ref()
+            .map(|ret| type_shape(ret, self.new_schemas));
+        let location = format!("{location}.response");
+        match (old_ret, new_ret) {
+            (Some(old_ret), Some(new_ret)) => {
+                // `undefined`, like `null`, is one more value that may
+                // come back.
+                if old.may_be_empty != new.may_be_empty {
+                    self.push(
+                        location.clone(),
+                        if new.may_be_empty {
+                            ChangeKind::NullableAdded
+                        } else {
+                            ChangeKind::NullableRemoved
+                        },
+                        new.may_be_empty,
+                        if new.may_be_empty {
+                            "may now be empty".to_string()
+                        } else {
+                            "is no longer ever empty".to_string()
+                        },
+                    );
+                }
+                self.shape(&location, &old_ret, &new_ret, Direction::Response)
+            }
+            (None, None) => {}
+            (old, new) => {
+                let describe = |ret: Option<Shape>| match ret {
+                    Some(ret) => ret.describe(),
+                    None => "nothing".to_string(),
+                };
+                self.push(
+                    location,
+                    ChangeKind::TypeChanged,
+                    true,
+                    format!(
+                        "response changed from {} to {}",
+                        describe(old),
+                        describe(new)
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Compares the fields of an object other than those it names.
+    fn additional(
+        &mut self,
+        location: &str,
+        old: Option<&Shape>,
+        new: Option<&Shape>,
+        direction: Direction,
+    ) {
+        match (old, new) {
+            (None, None) => {}
+            (None, Some(_)) => self.push(
+                location.to_string(),
+                ChangeKind::AdditionalPropertiesAdded,
+                direction == Direction::Response,
+                "other fields are now allowed".to_string(),
+            ),
+            (Some(_), None) => self.push(
+                location.to_string(),
+                ChangeKind::AdditionalPropertiesRemoved,
+                direction == Direction::Request,
+                "other fields are no longer allowed".to_string(),
+            ),
+            (Some(old), Some(new)) => {
+                self.shape(&format!("{location}[string]"), old, new, direction)
+            }
+        }
+    }
+
+    fn shape(&mut self, location: &str, old: &Shape, new: &Shape, direction: Direction) {
+        match (old, new) {
+            (Shape::String(old), Shape::String(new)) if !old.is_empty() && !new.is_empty() => {
+                for variant in old.difference(new) {
+                    self.push(
+                        location.to_string(),
+                        ChangeKind::EnumVariantRemoved,
+                        direction == Direction::Request,
+                        format!("enum variant `{variant}` removed"),
+                    );
+                }
+                for variant in new.difference(old) {
+                    self.push(
+                        location.to_string(),
+                        ChangeKind::EnumVariantAdded,
+                        direction == Direction::Response,
+                        format!("enum variant `{variant}` added"),
+                    );
+                }
+            }
+            (Shape::Nullable(old), Shape::Nullable(new)) => {
+                self.shape(location, old, new, direction);
+            }
+            // `null` is just one more value that may be passed in, or
+            // may come back.
+            (Shape::Nullable(old), new) => {
+                self.push(
+                    location.to_string(),
+                    ChangeKind::NullableRemoved,
+                    direction == Direction::Request,
+                    "no longer nullable".to_string(),
+                );
+                self.shape(location, old, new, direction);
+            }
+            (old, Shape::Nullable(new)) => {
+                self.push(
+                    location.to_string(),
+                    ChangeKind::NullableAdded,
+                    direction == Direction::Response,
+                    "now nullable".to_string(),
+                );
+                self.shape(location, old, new, direction);
+            }
+            (Shape::Array(old), Shape::Array(new)) => {
+                self.shape(&format!("{location}[]"), old, new, direction);
+            }
+            (
+                Shape::Object {
+                    fields: old,
+                    additional: old_additional,
+                },
+                Shape::Object {
+                    fields: new,
+                    additional: new_additional,
+                },
+            ) => {
+                let names = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+                for name in names {
+                    let location = format!("{location}.{name}");
+                    match (old.get(name), new.get(name)) {
+                        // a caller that still passes the field in gets
+                        // an excess property error.
+                        (Some(_), None) => self.push(
+                            location,
+                            ChangeKind::FieldRemoved,
+                            true,
+                            "field removed".to_string(),
+                        ),
+                        (None, Some((_, optional))) => self.push(
+                            location,
+                            ChangeKind::FieldAdded,
+                            direction == Direction::Request && !optional,
+                            if *optional {
+                                "optional field added".to_string()
+                            } else {
+                                "required field added".to_string()
+                            },
+                        ),
+                        (Some((old, old_optional)), Some((new, new_optional))) => {
+                            if *old_optional && !new_optional {
+                                self.push(
+                                    location.clone(),
+                                    ChangeKind::FieldRequired,
+                                    direction == Direction::Request,
+                                    "field is now required".to_string(),
+                                );
+                            } else if !old_optional && *new_optional {
+                                self.push(
+                                    location.clone(),
+                                    ChangeKind::FieldOptional,
+                                    direction == Direction::Response,
+                                    "field is now optional".to_string(),
+                                );
+                            }
+                            self.shape(&location, old, new, direction);
+                        }
+                        (None, None) => unreachable!(),
+                    }
+                }
+                self.additional(
+                    location,
+                    old_additional.as_deref(),
+                    new_additional.as_deref(),
+                    direction,
+                );
+            }
+            (old, new) if old == new => {}
+            (old, new) => {
+                // going from an enum to a plain string is only a
+                // widening; the other way around, only a narrowing.
+                // An ID is a narrower string, too.
+                let breaking = match (old, new) {
+                    (Shape::String(_) | Shape::Id(_), Shape::String(new)) if new.is_empty() => {
+                        direction == Direction::Response
+                    }
+                    (Shape::String(old), Shape::Id(_)) if old.is_empty() => {
+                        direction == Direction::Request
+                    }
+                    (Shape::String(_), Shape::String(_)) => direction == Direction::Request,
+                    _ => true,
+                };
+                self.push(
+                    location.to_string(),
+                    ChangeKind::TypeChanged,
+                    breaking,
+                    format!("type changed from {} to {}", old.describe(), new.describe()),
+                );
+            }
+        }
+    }
+}
+
+fn params(method: &Method) -> BTreeMap<&str, &Param> {
+    method
+        .parameters
+        .iter()
+        .map(|param| (param.id.as_str(), param))
+        .collect()
+}
+
+type Schemas = IndexMap<String, Schema>;
+
+fn param_shape(param: &Param, schemas: &Schemas) -> Shape {
+    // user IDs are rendered as plain strings, whatever the spec says
+    // about them; unless they are typed as IDs.
+    if param.kind.is_token_id() {
+        return token_id_shape(&param.ty);
+    }
+    type_shape(&param.ty, schemas)
+}
+
+fn token_id_shape(ty: &Type) -> Shape {
+    match ty {
+        Type::Id { name } => Shape::Id(name.clone()),
+        Type::Array { items } => Shape::Array(Box::new(match &**items {
+            Type::Id { name } => Shape::Id(name.clone()),
+            _ => Shape::String(BTreeSet::new()),
+        })),
+        Type::Nullable { inner } => Shape::Nullable(Box::new(token_id_shape(inner))),
+        _ => Shape::String(BTreeSet::new()),
+    }
+}
+
+/// Named schemas are compared by their contents, not their names;
+/// renaming a schema in the spec changes nothing in what the client
+/// accepts or returns.
+fn type_shape(ty: &Type, schemas: &Schemas) -> Shape { expand(ty, schemas, &mut Vec::new()) }
+
+/// Finds the shape of the type, expanding the schemas it references;
+/// except for those in `expanding`, which it is inside of already.
+fn expand<'a>(ty: &'a Type, schemas: &'a Schemas, expanding: &mut Vec<&'a str>) -> Shape {
+    match ty {
+        Type::String { enumeration } => Shape::String(enumeration.iter().cloned().collect()),
+        Type::Number | Type::Integer => Shape::Number,
+        Type::Boolean => Shape::Boolean,
+        Type::Id { name } => Shape::Id(name.clone()),
+        Type::Array { items } => Shape::Array(Box::new(expand(items, schemas, expanding))),
+        Type::Object(obj) => Shape::Object {
+            fields: obj
+                .properties
+                .iter()
+                .map(|prop| {
+                    (
+                        prop.id.clone(),
+                        (expand(&prop.ty, schemas, expanding), prop.optional),
+                    )
+                })
+                .collect(),
+            additional: obj.additional_properties.as_ref().map(|additional| {
+                Box::new(match additional {
+                    AdditionalProperties::Any => Shape::Unknown,
+                    AdditionalProperties::Typed { ty, .. } => expand(ty, schemas, expanding),
+                })
+            }),
+        },
+        Type::Union { variants, .. } => Shape::Union(
+            variants
+                .iter()
+                .map(|variant| expand(variant, schemas, expanding))
+                .collect(),
+        ),
+        Type::Intersection { parts } => Shape::Intersection(
+            parts
+                .iter()
+                .map(|part| expand(part, schemas, expanding))
+                .collect(),
+        ),
+        Type::Ref { name } if expanding.contains(&name.as_str()) => Shape::Recursive(name.clone()),
+        Type::Ref { name } => match schemas.get(name) {
+            Some(schema) => {
+                expanding.push(name);
+                let shape = expand(&schema.ty, schemas, expanding);
+                expanding.pop();
+                shape
+            }
+            None => Shape::Unknown,
+        },
+        Type::Nullable { inner } => Shape::Nullable(Box::new(expand(inner, schemas, expanding))),
+        Type::Unknown => Shape::Unknown,
+    }
+}
@@ -1,9 +1,14 @@
+use crate::config::{Config, TokenParamLocation, TokenRole};
+use anyhow::Context as _;
 use heck::ToLowerCamelCase as _;
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
 #[derive(Debug)]
 pub struct Extract<'o> {
-    pub namespaces: HashMap<String, ExtractNamespace<'o>>,
+    /// The namespaces, in the order the spec declares their tags in;
+    /// any namespace without a tag comes after, in the order of its
+    /// first operation.
+    pub namespaces: IndexMap<String, ExtractNamespace<'o>>,
 }
 
 #[derive(Default, Debug)]
@@ -15,27 +20,69 @@ pub struct ExtractNamespace<'o> {
 #[derive(Debug)]
 pub struct ExtractNamespaceMethod<'o> {
     pub name: String,
+    pub operation_id: &'o str,
     pub path: &'o str,
     pub method: &'o str,
     pub description: Option<&'o str>,
-    pub parameters: HashMap<String, ExtractNamespaceMethodParam<'o>>,
-    pub ret: Option<&'o openapiv3::ObjectType>,
+    /// The parameters, in the order the spec declares them in; the
+    /// query parameters first, then the body properties.
+    pub parameters: IndexMap<String, ExtractNamespaceMethodParam<'o>>,
+    pub ret: Option<&'o openapiv3::Schema>,
+    /// The named schemas that the request body, and the response
+    /// body, are a `$ref` to, if they are; as those are inlined into
+    /// the method, so are the overrides for the schema.
+    pub body_schema: Option<&'o str>,
+    pub ret_schema: Option<&'o str>,
+    /// The status codes the spec documents for success, in order;
+    /// see [`Extract::success_responses`].
+    pub statuses: Vec<u16>,
+    /// Whether some of the success responses have no body, while
+    /// another has one.
+    pub may_be_empty: bool,
     pub download: bool,
-    pub requires_scopes: Option<Vec<&'o str>>,
+    /// The error responses the spec documents, by status code.
+    pub errors: Vec<ExtractNamespaceMethodError<'o>>,
+    /// The sets of scopes the operation requires, any one of which is
+    /// enough; empty if it requires none.  See [`crate::scopes`].
+    pub requires_scopes: Vec<Vec<&'o str>>,
     // op: &'o openapiv3::Operation,
 }
 
+#[derive(Debug)]
+pub struct ExtractNamespaceMethodError<'o> {
+    pub status: u16,
+    /// What the status means for this operation.
+    pub description: &'o str,
+}
+
 #[derive(Debug)]
 pub struct ExtractNamespaceMethodParam<'o> {
     pub id: String,
     pub name: String,
-    pub ty: &'o openapiv3::Type,
+    pub ty: &'o openapiv3::Schema,
     pub description: Option<&'o str>,
     pub optional: bool,
     pub kind: ParamKind,
+    /// What decided the kind.
+    pub classification: Classification<'o>,
+}
+
+/// What decided the kind of a parameter; see
+/// [`crate::config::TokenParamConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Classification<'o> {
+    /// The rule with this name matched it.
+    Rule(&'o str),
+    /// The operation's `token-params` override names it.
+    Override,
+    /// No rule matched it, so it is a plain parameter.
+    NoRule,
+    /// It is a path or header parameter, which are never user IDs.
+    Fixed,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum ParamKind {
     Query,
     QueryCurrentUserToken,
@@ -43,9 +90,39 @@ pub enum ParamKind {
     Body,
     BodyCurrentUserToken,
     BodyUserToken,
+    /// Substituted into the path.
+    Path,
+    /// Sent as a request header.
+    Header,
+}
+
+impl std::fmt::Display for ParamKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ParamKind::Query => "query",
+            ParamKind::QueryCurrentUserToken => "query-current-user-token",
+            ParamKind::QueryUserToken => "query-user-token",
+            ParamKind::Body => "body",
+            ParamKind::BodyCurrentUserToken => "body-current-user-token",
+            ParamKind::BodyUserToken => "body-user-token",
+            ParamKind::Path => "path",
+            ParamKind::Header => "header",
+        })
+    }
 }
 
 impl ParamKind {
+    fn new(location: TokenParamLocation, role: TokenRole) -> Self {
+        match (location, role) {
+            (TokenParamLocation::Query, TokenRole::CurrentUser) => ParamKind::QueryCurrentUserToken,
+            (TokenParamLocation::Query, TokenRole::User) => ParamKind::QueryUserToken,
+            (TokenParamLocation::Query, TokenRole::Plain) => ParamKind::Query,
+            (TokenParamLocation::Body, TokenRole::CurrentUser) => ParamKind::BodyCurrentUserToken,
+            (TokenParamLocation::Body, TokenRole::User) => ParamKind::BodyUserToken,
+            (TokenParamLocation::Body, TokenRole::Plain) => ParamKind::Body,
+        }
+    }
+
     pub fn is_token_id(&self) -> bool {
         matches!(
             self,
@@ -56,8 +133,10 @@ impl ParamKind {
         )
     }
 
-    pub fn is_active(&self) -> bool {
-        !matches!(
+    /// Checks if the parameter defaults to the ID of the token's
+    /// user, if the caller leaves it out.
+    pub fn is_current_user(&self) -> bool {
+        matches!(
             self,
             ParamKind::QueryCurrentUserToken | ParamKind::BodyCurrentUserToken
         )
@@ -71,9 +150,13 @@ impl<'o> Extract<'o> {
     /// OpenAPI spec here.  We can do this because we're _only_
     /// expecting the Twitch OpenAPI spec here; if we were not,
     /// this would be a bad idea.
-    pub fn new(api: &'o openapiv3::OpenAPI) -> Result<Self, anyhow::Error> {
+    ///
+    /// The configuration decides which parameters are user IDs, and
+    /// may override the name, namespace or description of individual
+    /// operations (or skip them entirely).
+    pub fn new(api: &'o openapiv3::OpenAPI, config: &'o Config) -> Result<Self, anyhow::Error> {
         let mut this = Self {
-            namespaces: HashMap::new(),
+            namespaces: IndexMap::new(),
         };
         for tag in &api.tags {
             this.namespaces
@@ -82,287 +165,382 @@ impl<'o> Extract<'o> {
         }
 
         for (path, method, op) in api.operations() {
-            this.extract_op(path, method, op, api);
+            this.extract_op(path, method, op, api, config)
+                .with_context(|| format!("when extracting the operation {method} {path}"))?;
         }
 
         Ok(this)
     }
 
+    /// Checks that every operation the configuration (or the scopes
+    /// file) has an entry for is in the spec; an entry for an
+    /// operation that isn't is most likely a typo.
+    ///
+    /// This isn't part of [`Extract::new`], as an older spec may well
+    /// lack an operation that the configuration has since been
+    /// written for; `diff` compares against one.
+    pub fn check_overrides(api: &openapiv3::OpenAPI, config: &Config) -> Result<(), anyhow::Error> {
+        if let Some(id) = config.operations.keys().find(|id| {
+            !api.operations()
+                .any(|(_, _, op)| op.operation_id.as_ref() == Some(*id))
+        }) {
+            anyhow::bail!(
+                "the config has an override for the operation `{id}`, but the spec has no such \
+                 operation"
+            );
+        }
+        if let Some(id) = config.scopes.operations.keys().find(|id| {
+            !api.operations()
+                .any(|(_, _, op)| op.operation_id.as_ref() == Some(*id))
+        }) {
+            anyhow::bail!(
+                "the scopes file has an entry for the operation `{id}`, but the spec has no such \
+                 operation"
+            );
+        }
+        Ok(())
+    }
+
     fn extract_op(
         &mut self,
         path: &'o str,
         method: &'o str,
         op: &'o openapiv3::Operation,
         api: &'o openapiv3::OpenAPI,
-    ) {
-        let namespace_name = op
-            .tags
-            .first()
-            .unwrap_or_else(|| {
-                panic!(
-                    "Operation at {} {} has no tags, so we cannot determine the namespace",
-                    method, path
-                )
-            })
-            .to_lower_camel_case();
+        config: &'o Config,
+    ) -> Result<(), anyhow::Error> {
+        let operation_id = op
+            .operation_id
+            .as_deref()
+            .context("the operation has no operationId, so we cannot determine the method name")?;
+        let overrides = config.operations.get(operation_id);
+        if overrides.is_some_and(|o| o.skip) {
+            return Ok(());
+        }
+
+        let namespace_name = match overrides.and_then(|o| o.namespace.as_deref()) {
+            Some(namespace) => namespace.to_string(),
+            None => op
+                .tags
+                .first()
+                .context(
+                    "the operation has no tags, so we cannot determine the namespace (give it one \
+                     with `operations.<id>.namespace`)",
+                )?
+                .to_lower_camel_case(),
+        };
         let namespace = self.namespaces.entry(namespace_name).or_default();
 
-        let name = op
-            .operation_id
-            .as_ref()
-            .unwrap_or_else(|| {
-                panic!(
-                    "Operation at {} {} has no operationId, so we cannot determine the method name",
-                    method, path
-                )
-            })
-            .to_lower_camel_case();
+        let name = match overrides.and_then(|o| o.name.as_deref()) {
+            Some(name) => name.to_string(),
+            None => operation_id.to_lower_camel_case(),
+        };
+        let successes = Self::success_responses(op)?;
+        // the body is that of the first response that has one; the
+        // others (say, a 204) have nothing to parse.
+        let success = successes
+            .iter()
+            .find(|(_, response)| !response.content.is_empty())
+            .or(successes.first())
+            .copied();
 
         namespace.methods.push(ExtractNamespaceMethod {
             name,
+            operation_id,
             path,
             method,
-            description: op.description.as_deref(),
-            // the OpenAPI spec lies.
-            // requires_scopes: Self::extract_scopes(op),
-            requires_scopes: None,
-            parameters: Self::extract_params(op, api),
-            ret: Self::extract_response(op, api),
-            download: op
-                .responses
-                .responses
-                .get(&openapiv3::StatusCode::Code(200))
-                .map_or(false, |x| {
-                    x.as_item()
-                        .map_or(false, |x| x.content.get("application/json").is_none())
-                }),
+            description: overrides
+                .and_then(|o| o.description.as_deref())
+                .or(op.description.as_deref()),
+            // the OpenAPI spec lies, so it gets corrected.
+            requires_scopes: config
+                .scopes
+                .resolve(operation_id, Self::extract_scopes(op, api)),
+            parameters: Self::extract_params(path, op, operation_id, api, config)?,
+            ret: match success {
+                Some((_, response)) => Self::extract_response(response, api)?,
+                None => None,
+            },
+            body_schema: match op.request_body.as_ref() {
+                Some(req) => schema_name(&resolve_reference_request_body(req, api)?.content),
+                None => None,
+            },
+            ret_schema: success.and_then(|(_, response)| schema_name(&response.content)),
+            statuses: successes.iter().map(|(status, _)| *status).collect(),
+            may_be_empty: success.is_some_and(|(_, response)| !response.content.is_empty())
+                && successes
+                    .iter()
+                    .any(|(_, response)| response.content.is_empty()),
+            // a response with content, none of which is JSON; a
+            // response without any content (say, a 204) is nothing
+            // at all.
+            download: success.is_some_and(|(_, response)| {
+                !response.content.is_empty() && response.content.get("application/json").is_none()
+            }),
+            errors: Self::extract_errors(op)?,
         });
+        Ok(())
     }
 
-    fn extract_response(
+    /// Finds the responses the operation gives on success: those for
+    /// the 2xx status codes the spec declares, in status order.
+    /// Twitch doesn't use a default response, or status code ranges,
+    /// so we don't either.
+    fn success_responses(
         op: &'o openapiv3::Operation,
-        api: &'o openapiv3::OpenAPI,
-    ) -> Option<&'o openapiv3::ObjectType> {
-        // twitch openapi does not use a default response, so we skip directly
-        // to status codes.
-        let response = op
+    ) -> Result<Vec<(u16, &'o openapiv3::Response)>, anyhow::Error> {
+        let mut responses = op
             .responses
             .responses
-            .get(&openapiv3::StatusCode::Code(200))?;
-        let Some(response) = response.as_item() else {
-            unreachable!("Operation 200 response is a $ref, which is not supported")
-        };
-        let content = response.content.get("application/json")?;
-        let Some(schema_ref) = content.schema.as_ref() else {
-            unreachable!(
-                "Operation 200 response does not have a schema ({:?})",
-                op.operation_id
-            )
-        };
-        let Some(schema) = resolve_reference_schema(schema_ref, api) else {
-            unreachable!(
-                "Operation 200 response schema is a $ref, but we could not resolve it ({:?})",
-                op.operation_id
-            )
-        };
-        let openapiv3::SchemaKind::Type(ty) = &schema.schema_kind else {
-            unreachable!(
-                "Operation 200 response schema is not a single type? ({:?})",
-                op.operation_id
-            )
-        };
-        let openapiv3::Type::Object(obj) = ty else {
-            unreachable!(
-                "Operation 200 response schema is not an object? ({:?})",
-                op.operation_id
-            )
-        };
+            .iter()
+            .filter_map(|(status, response)| match status {
+                openapiv3::StatusCode::Code(status @ 200..=299) => Some((*status, response)),
+                _ => None,
+            })
+            .map(|(status, response)| {
+                let Some(response) = response.as_item() else {
+                    anyhow::bail!("the {status} response is a $ref, which is not supported")
+                };
+                Ok((status, response))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        responses.sort_by_key(|(status, _)| *status);
+        Ok(responses)
+    }
 
-        Some(obj)
+    /// Collects the 4xx and 5xx responses, in status order.
+    fn extract_errors(
+        op: &'o openapiv3::Operation,
+    ) -> Result<Vec<ExtractNamespaceMethodError<'o>>, anyhow::Error> {
+        let mut errors = op
+            .responses
+            .responses
+            .iter()
+            .filter_map(|(status, response)| match status {
+                openapiv3::StatusCode::Code(status @ 400..=599) => Some((*status, response)),
+                _ => None,
+            })
+            .map(|(status, response)| {
+                let Some(response) = response.as_item() else {
+                    anyhow::bail!("the {status} response is a $ref, which is not supported")
+                };
+                Ok(ExtractNamespaceMethodError {
+                    status,
+                    description: &response.description,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        errors.sort_by_key(|error| error.status);
+        Ok(errors)
+    }
+
+    fn extract_response(
+        response: &'o openapiv3::Response,
+        api: &'o openapiv3::OpenAPI,
+    ) -> Result<Option<&'o openapiv3::Schema>, anyhow::Error> {
+        let Some(content) = response.content.get("application/json") else {
+            return Ok(None);
+        };
+        let schema = content
+            .schema
+            .as_ref()
+            .context("the success response does not have a schema")?;
+        let schema = resolve_reference_schema(schema, api)
+            .context("the success response schema is a $ref, but we could not resolve it")?;
+        Ok(Some(schema))
     }
 
-    #[expect(dead_code)]
-    fn extract_scopes(op: &'o openapiv3::Operation) -> Option<Vec<&'o str>> {
+    /// The scopes the spec says the operation requires, falling back
+    /// to the spec's default.  Each security requirement is one set
+    /// of scopes, all of which are needed (across every scheme in
+    /// it).
+    fn extract_scopes(
+        op: &'o openapiv3::Operation,
+        api: &'o openapiv3::OpenAPI,
+    ) -> Vec<Vec<&'o str>> {
         op.security
             .as_ref()
-            .map(|x| {
-                x.iter()
-                    .flat_map(|x| x.values())
-                    .flat_map(|x| x.iter())
-                    .map(|x| x.as_str())
-                    .collect::<Vec<_>>()
-            })
-            .filter(|it| !it.is_empty())
+            .or(api.security.as_ref())
+            .into_iter()
+            .flatten()
+            .map(|requirement| requirement.values().flatten().map(String::as_str).collect())
+            .collect()
     }
 
+    /// Extracts the parameters of the operation, including those
+    /// declared on its path, which it may override.
     fn extract_params(
+        path: &'o str,
         op: &'o openapiv3::Operation,
+        operation_id: &str,
         api: &'o openapiv3::OpenAPI,
-    ) -> HashMap<String, ExtractNamespaceMethodParam<'o>> {
-        let mut parameters = op
-            .parameters
-            .iter()
-            .flat_map(|v| v.as_item())
-            .map(|param| {
-                let param = ExtractNamespaceMethodParam::extract_query(param);
-                (param.id.clone(), param)
-            })
-            .collect::<HashMap<_, _>>();
-
-        if let Some(req) = op.request_body.as_ref() {
-            let req = req.as_item().unwrap();
-            ExtractNamespaceMethodParam::extract_body(&mut parameters, req, api);
+        config: &'o Config,
+    ) -> Result<IndexMap<String, ExtractNamespaceMethodParam<'o>>, anyhow::Error> {
+        let classify = Classifier {
+            config,
+            operation_id,
+        };
+        let shared = api
+            .paths
+            .paths
+            .get(path)
+            .and_then(|item| item.as_item())
+            .map(|item| &item.parameters[..])
+            .unwrap_or_default();
+
+        let mut declared = IndexMap::new();
+        for param in shared.iter().chain(&op.parameters) {
+            let param = resolve_reference_parameter(param, api)?;
+            let data = param.parameter_data_ref();
+            // an operation's own parameters override the ones on the
+            // path, with the same name and location.
+            declared.insert((std::mem::discriminant(param), data.name.as_str()), param);
         }
-        parameters
-    }
-}
-
-impl ExtractNamespaceMethod<'_> {
-    #[must_use]
-    pub fn has_active_params(&self) -> bool {
-        self.parameters.iter().any(|(_, c)| {
-            !matches!(
-                c.kind,
-                ParamKind::BodyCurrentUserToken | ParamKind::QueryCurrentUserToken
-            )
-        })
-    }
 
-    /// Checks if any of the parameters have a body kind.
-    ///
-    /// This function iterates over the values of the `parameters`
-    /// field and checks if any of them
-    /// match the `ParamKind::Body`,
-    /// `ParamKind::BodyCurrentUserToken`, or
-    /// `ParamKind::BodyUserToken` variants.
-    ///
-    /// # Returns
-    ///
-    /// * `true` - if any parameter is of any body kind.
-    /// * `false` - otherwise.
-    #[must_use]
-    pub fn has_body(&self) -> bool {
-        self.parameters.values().any(|x| {
-            matches!(
-                x.kind,
-                ParamKind::Body | ParamKind::BodyCurrentUserToken | ParamKind::BodyUserToken
-            )
-        })
-    }
+        let mut parameters = IndexMap::new();
+        for param in declared.into_values() {
+            let param = ExtractNamespaceMethodParam::extract_parameter(param, api, &classify)?;
+            if parameters.contains_key(&param.id) {
+                anyhow::bail!("more than one parameter is named `{}`", param.id);
+            }
+            parameters.insert(param.id.clone(), param);
+        }
 
-    pub fn body_params(&self) -> impl Iterator<Item = &ExtractNamespaceMethodParam<'_>> {
-        self.parameters.values().filter(|x| {
-            matches!(
-                x.kind,
-                ParamKind::Body | ParamKind::BodyCurrentUserToken | ParamKind::BodyUserToken
-            )
-        })
-    }
+        if let Some(req) = op.request_body.as_ref() {
+            let req = resolve_reference_request_body(req, api)?;
+            ExtractNamespaceMethodParam::extract_body(&mut parameters, req, api, &classify)
+                .context("in the request body")?;
+        }
 
-    pub fn query_params(&self) -> impl Iterator<Item = &ExtractNamespaceMethodParam<'_>> {
-        self.parameters.values().filter(|x| {
-            matches!(
-                x.kind,
-                ParamKind::Query | ParamKind::QueryCurrentUserToken | ParamKind::QueryUserToken
-            )
-        })
+        let overrides = config
+            .operations
+            .get(operation_id)
+            .into_iter()
+            .flat_map(|op| op.token_params.keys());
+        for name in overrides {
+            if !parameters.values().any(|param| {
+                param.name == *name && !matches!(param.kind, ParamKind::Path | ParamKind::Header)
+            }) {
+                anyhow::bail!(
+                    "the config gives a role to the parameter `{name}`, but the operation has no \
+                     such query or body parameter"
+                );
+            }
+        }
+        Ok(parameters)
     }
 }
 
 impl<'o> ExtractNamespaceMethodParam<'o> {
-    fn extract_query(param: &'o openapiv3::Parameter) -> Self {
-        let data = match param {
-            openapiv3::Parameter::Query { parameter_data, .. } => parameter_data,
-            openapiv3::Parameter::Header { .. } => {
-                unreachable!("the twitch api does not use header parameters; this is a bug");
+    fn extract_parameter(
+        param: &'o openapiv3::Parameter,
+        api: &'o openapiv3::OpenAPI,
+        classify: &Classifier<'o, '_>,
+    ) -> Result<Self, anyhow::Error> {
+        let data = param.parameter_data_ref();
+        let id = data.name.to_lower_camel_case();
+        let ty = pull_schema(&data.format, api)
+            .with_context(|| format!("in the parameter `{}`", data.name))?;
+        let optional = !data.required;
+        let (kind, classification) = match param {
+            openapiv3::Parameter::Query { .. } => {
+                let (role, classification) = classify
+                    .classify(
+                        TokenParamLocation::Query,
+                        &data.name,
+                        data.description.as_deref(),
+                        ty,
+                    )
+                    .with_context(|| format!("in the parameter `{}`", data.name))?;
+                (
+                    ParamKind::new(TokenParamLocation::Query, role),
+                    classification,
+                )
             }
+            openapiv3::Parameter::Path { .. } => (ParamKind::Path, Classification::Fixed),
+            openapiv3::Parameter::Header { .. } => (ParamKind::Header, Classification::Fixed),
             openapiv3::Parameter::Cookie { .. } => {
-                unreachable!("the twitch api does not use cookie parameters; this is a bug");
-            }
-            openapiv3::Parameter::Path { .. } => {
-                unreachable!("the twitch api does not use path parameters; this is a bug");
+                anyhow::bail!(
+                    "the parameter `{}` is a cookie, which a client cannot send",
+                    data.name
+                );
             }
         };
 
-        let id = data.name.to_lower_camel_case();
-        let ty = pull_type(&data.format);
-        let optional = !data.required;
-        // this is hacky, and I want a better way.
-        let kind = if is_current_user_param(data, ty) {
-            ParamKind::QueryCurrentUserToken
-        } else if data.name == "user_id" || data.name == "broadcaster_id" {
-            ParamKind::QueryUserToken
-        } else {
-            ParamKind::Query
-        };
-
-        Self {
+        Ok(Self {
             id,
             name: data.name.clone(),
             description: data.description.as_deref(),
-            optional,
+            // path parameters are always required, whatever the spec
+            // says.
+            optional: optional && kind != ParamKind::Path,
             ty,
             kind,
-        }
+            classification,
+        })
     }
 
     fn extract_body(
-        params: &mut HashMap<String, Self>,
+        params: &mut IndexMap<String, Self>,
         req: &'o openapiv3::RequestBody,
         api: &'o openapiv3::OpenAPI,
-    ) {
+        classify: &Classifier<'o, '_>,
+    ) -> Result<(), anyhow::Error> {
         let Some(content) = req.content.get("application/json") else {
-            unreachable!("request body does not have a json content type")
-        };
-        let Some(schema_ref) = content.schema.as_ref() else {
-            unreachable!("request body does not have a schema")
-        };
-        let Some(schema) = resolve_reference_schema(schema_ref, api) else {
-            unreachable!("request body schema is a $ref, but we could not resolve it")
-        };
-        let openapiv3::SchemaKind::Type(ty) = &schema.schema_kind else {
-            unreachable!("request body schema is not a single type?")
-        };
-        let openapiv3::Type::Object(obj) = ty else {
-            unreachable!("request body schema is not an object?")
+            anyhow::bail!(
+                "the request body is not JSON (it is {}), which the client cannot send",
+                req.content
+                    .keys()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
         };
+        let schema_ref = content
+            .schema
+            .as_ref()
+            .context("the request body does not have a schema")?;
+        let schema = resolve_reference_schema(schema_ref, api)
+            .context("the request body is a $ref, but we could not resolve it")?;
+        // the body may be split up with `allOf`; its properties are
+        // the properties of all of its parts.
+        let mut objects = Vec::new();
+        collect_objects(schema, api, &mut objects)?;
+        let required = objects
+            .iter()
+            .flat_map(|obj| &obj.required)
+            .collect::<Vec<_>>();
 
-        for (name, prop) in &obj.properties {
-            let Some(prop_schema) = prop.as_item() else {
-                unreachable!("request body property is a $ref, which is not supported")
-            };
+        for (name, prop) in objects.iter().flat_map(|obj| &obj.properties) {
+            let prop_schema = resolve_reference_boxed_schema(prop, api).with_context(|| {
+                format!("the body property `{name}` is a $ref, but we could not resolve it")
+            })?;
             let id = name.to_lower_camel_case();
-            let openapiv3::SchemaKind::Type(ty) = &prop_schema.schema_kind else {
-                unreachable!(
-                    "the schema for the parameter is not a single type (saw {:?})",
-                    schema.schema_kind
+            let optional = !required.contains(&name);
+            let description = prop_schema.schema_data.description.as_deref();
+            let (role, classification) = classify
+                .classify(TokenParamLocation::Body, name, description, prop_schema)
+                .with_context(|| format!("in the body property `{name}`"))?;
+
+            if params.contains_key(&id) {
+                anyhow::bail!(
+                    "the body property `{name}` has the same name as another parameter, `{id}`"
                 );
-            };
-            let optional = !obj.required.contains(name);
-            let kind = if is_current_user_body(name, prop_schema) {
-                ParamKind::BodyCurrentUserToken
-            } else if name == "user_id" || name == "broadcaster_id" || name == "moderator_id" {
-                ParamKind::BodyUserToken
-            } else {
-                ParamKind::Body
-            };
-
-            let prev = params.insert(
+            }
+            params.insert(
                 id.clone(),
                 Self {
                     id: id.clone(),
                     name: name.clone(),
-                    description: prop_schema.schema_data.description.as_deref(),
+                    description,
                     optional,
-                    ty,
-                    kind,
+                    ty: prop_schema,
+                    kind: ParamKind::new(TokenParamLocation::Body, role),
+                    classification,
                 },
             );
-
-            if prev.is_some() {
-                panic!("duplicate parameter id {}", id);
-            }
         }
+        Ok(())
     }
 }
 
@@ -383,7 +561,18 @@ pub(crate) fn resolve_reference_schema<'o>(
     }
 }
 
-pub(crate) fn resolve_reference_boxed_schema<'o>(
+/// The name of the schema that the JSON content is a `$ref` to, if
+/// it is one.
+fn schema_name(content: &IndexMap<String, openapiv3::MediaType>) -> Option<&str> {
+    match content.get("application/json")?.schema.as_ref()? {
+        openapiv3::ReferenceOr::Reference { reference } => {
+            reference.strip_prefix("#/components/schemas/")
+        }
+        openapiv3::ReferenceOr::Item(_) => None,
+    }
+}
+
+fn resolve_reference_boxed_schema<'o>(
     schema: &'o openapiv3::ReferenceOr<Box<openapiv3::Schema>>,
     api: &'o openapiv3::OpenAPI,
 ) -> Option<&'o openapiv3::Schema> {
@@ -400,39 +589,142 @@ pub(crate) fn resolve_reference_boxed_schema<'o>(
     }
 }
 
-fn is_current_user_param(data: &openapiv3::ParameterData, ty: &openapiv3::Type) -> bool {
-    data.description.as_ref().is_some_and(|x| {
-        x.contains("the user ID in the access token")
-            || x.contains("the user ID in the user access token")
-    }) && data.name.ends_with("_id")
-        && matches!(ty, openapiv3::Type::String(_))
+/// Collects the objects that make up the schema: the schema itself,
+/// if it is an object, or every part of it, if it is an `allOf`.
+///
+/// The properties of the objects become the parameters of the
+/// method; so a body that is a `oneOf`/`anyOf`, which has no one set
+/// of properties, is an error.
+fn collect_objects<'o>(
+    schema: &'o openapiv3::Schema,
+    api: &'o openapiv3::OpenAPI,
+    objects: &mut Vec<&'o openapiv3::ObjectType>,
+) -> Result<(), anyhow::Error> {
+    match &schema.schema_kind {
+        openapiv3::SchemaKind::Type(openapiv3::Type::Object(obj)) => objects.push(obj),
+        openapiv3::SchemaKind::AllOf { all_of } => {
+            for part in all_of {
+                let part = resolve_reference_schema(part, api)
+                    .context("a part of the allOf is a $ref, but we could not resolve it")?;
+                collect_objects(part, api, objects)?;
+            }
+        }
+        openapiv3::SchemaKind::OneOf { .. } | openapiv3::SchemaKind::AnyOf { .. } => {
+            anyhow::bail!(
+                "the request body is a oneOf/anyOf, which cannot be split up into parameters; \
+                 only an object, or an allOf of objects, can"
+            )
+        }
+        other => anyhow::bail!(
+            "the request body is not an object, or an allOf of objects (saw {other:?})"
+        ),
+    }
+    Ok(())
+}
+
+/// Decides the role of the query and body parameters of an
+/// operation; see [`crate::config::TokenParamConfig`].
+struct Classifier<'o, 'i> {
+    config: &'o Config,
+    operation_id: &'i str,
+}
+
+impl<'o> Classifier<'o, '_> {
+    fn classify(
+        &self,
+        location: TokenParamLocation,
+        name: &str,
+        description: Option<&str>,
+        schema: &openapiv3::Schema,
+    ) -> Result<(TokenRole, Classification<'o>), anyhow::Error> {
+        let overridden = self
+            .config
+            .operations
+            .get(self.operation_id)
+            .and_then(|op| op.token_params.get(name));
+        if let Some(&role) = overridden {
+            if !role_fits(role, schema) {
+                anyhow::bail!(
+                    "the config makes the parameter a `{role}` parameter, but its type does not \
+                     allow it"
+                );
+            }
+            return Ok((role, Classification::Override));
+        }
+
+        // a rule whose role doesn't suit the type of the parameter
+        // doesn't match it.
+        Ok(self
+            .config
+            .token_params
+            .rules
+            .iter()
+            .find(|rule| rule.matches(location, name, description) && role_fits(rule.role, schema))
+            .map_or((TokenRole::Plain, Classification::NoRule), |rule| {
+                (rule.role, Classification::Rule(&rule.name))
+            }))
+    }
 }
 
-fn is_current_user_body(name: &str, schema: &openapiv3::Schema) -> bool {
-    schema.schema_data.description.as_ref().is_some_and(|x| {
-        x.contains("the user ID in the access token")
-            || x.contains("the user ID in the user access token")
-    }) && name.ends_with("_id")
-        && matches!(
+fn role_fits(role: TokenRole, schema: &openapiv3::Schema) -> bool {
+    use openapiv3::{SchemaKind, Type};
+    match role {
+        TokenRole::Plain => true,
+        TokenRole::CurrentUser => matches!(schema.schema_kind, SchemaKind::Type(Type::String(_))),
+        TokenRole::User => matches!(
             schema.schema_kind,
-            openapiv3::SchemaKind::Type(openapiv3::Type::String(_))
-        )
+            SchemaKind::Type(Type::String(_) | Type::Array(_))
+        ),
+    }
 }
 
-fn pull_type(schema: &openapiv3::ParameterSchemaOrContent) -> &openapiv3::Type {
+fn pull_schema<'o>(
+    schema: &'o openapiv3::ParameterSchemaOrContent,
+    api: &'o openapiv3::OpenAPI,
+) -> Result<&'o openapiv3::Schema, anyhow::Error> {
     let openapiv3::ParameterSchemaOrContent::Schema(schema) = &schema else {
-        unreachable!(
-            "the given parameter at is not a string? twitch api does not use content parameters..."
-        );
-    };
-    let Some(schema) = schema.as_item() else {
-        unreachable!("the schema for the parameter is a $ref, which is not supported");
+        anyhow::bail!("the parameter has content instead of a schema, which is not supported");
     };
-    let openapiv3::SchemaKind::Type(ty) = &schema.schema_kind else {
-        unreachable!(
-            "the schema for the parameter is not a single type (saw {:?})",
-            schema.schema_kind
-        );
-    };
-    ty
+    resolve_reference_schema(schema, api)
+        .context("the schema for the parameter is a $ref, but we could not resolve it")
+}
+
+fn resolve_reference_parameter<'o>(
+    param: &'o openapiv3::ReferenceOr<openapiv3::Parameter>,
+    api: &'o openapiv3::OpenAPI,
+) -> Result<&'o openapiv3::Parameter, anyhow::Error> {
+    match param {
+        openapiv3::ReferenceOr::Item(param) => Ok(param),
+        openapiv3::ReferenceOr::Reference { reference } => {
+            let param = reference
+                .strip_prefix("#/components/parameters/")
+                .and_then(|name| {
+                    api.components
+                        .as_ref()
+                        .and_then(|components| components.parameters.get(name))
+                })
+                .with_context(|| format!("could not resolve the parameter `{reference}`"))?;
+            resolve_reference_parameter(param, api)
+        }
+    }
+}
+
+fn resolve_reference_request_body<'o>(
+    body: &'o openapiv3::ReferenceOr<openapiv3::RequestBody>,
+    api: &'o openapiv3::OpenAPI,
+) -> Result<&'o openapiv3::RequestBody, anyhow::Error> {
+    match body {
+        openapiv3::ReferenceOr::Item(body) => Ok(body),
+        openapiv3::ReferenceOr::Reference { reference } => {
+            let body = reference
+                .strip_prefix("#/components/requestBodies/")
+                .and_then(|name| {
+                    api.components
+                        .as_ref()
+                        .and_then(|components| components.request_bodies.get(name))
+                })
+                .with_context(|| format!("could not resolve the request body `{reference}`"))?;
+            resolve_reference_request_body(body, api)
+        }
+    }
 }
@@ -1,37 +1,416 @@
 use anyhow::Context as _;
-use std::path::PathBuf;
+use sha2::Digest as _;
+use std::path::{Path, PathBuf};
 
-const RAW_JSON_FILE: &str =
+/// Where we download the spec from, unless told otherwise.
+pub const RAW_JSON_FILE: &str =
     "https://github.com/DmitryScaletta/twitch-api-swagger/raw/main/openapi.json";
 
-pub fn load<P: Into<PathBuf>>(path: Option<P>) -> Result<openapiv3::OpenAPI, anyhow::Error> {
-    let path = attempt_download(path)?;
-    let file = std::fs::File::open(&path).context("when opening openapi file")?;
-    let reader = std::io::BufReader::new(file);
-    let mut api = serde_json::from_reader::<_, serde_json::Value>(reader)
-        .context("when parsing openapi file")?;
-    let point = api
-        .pointer_mut("/components/schemas/UpdateUserExtensionsBody/properties/data")
-        .expect("Failed to find UpdateUserExtensionsBody");
-    let point_object = point
-        .as_object_mut()
-        .expect("Failed to cast UpdateUserExtensionsBody to object");
-
-    assert!(point_object.get("type").is_none());
-    point_object.insert(
-        "type".to_string(),
-        serde_json::Value::String("object".to_string()),
-    );
-
-    serde_json::from_value(api).context("when deserializing openapi file")
-}
-
-fn attempt_download<P: Into<PathBuf>>(path: Option<P>) -> Result<PathBuf, anyhow::Error> {
-    let path = path
-        .map(|p| p.into())
-        .unwrap_or_else(|| PathBuf::from("./openapi.json"));
+/// The directory containing the patches we apply to the upstream
+/// spec, relative to the root of the repository.
+pub const DEFAULT_PATCH_DIR: &str = "./scripts/twitch/patches";
+
+/// The lockfile recording the spec used for the last generation,
+/// relative to the root of the repository.
+pub const DEFAULT_LOCK_FILE: &str = "./scripts/twitch/openapi.lock.json";
+
+/// The directory we cache downloaded specs (and their HTTP metadata)
+/// in, relative to the root of the repository.
+pub const DEFAULT_CACHE_DIR: &str = "./.cache/twitch";
+
+/// Where, and how, to load the OpenAPI spec from.
+#[derive(Debug, Default)]
+pub struct LoadOptions {
+    /// The path to the spec.  Defaults to `./openapi.json`.  If this
+    /// is `-`, the spec is read from stdin instead; such a spec is
+    /// never downloaded, nor checked against the lockfile, and so is
+    /// refused in frozen mode, if there is a lockfile to check.
+    pub path: Option<PathBuf>,
+    /// The URL to download the spec from, if it does not exist.
+    /// Defaults to [`RAW_JSON_FILE`].
+    pub source: Option<String>,
+    /// The directory of patches to apply to the spec.
+    pub patches: Option<PathBuf>,
+    /// The directory of overlays to merge into the spec, after the
+    /// patches have been applied.  See [`crate::overlay`].
+    pub overlays: Option<PathBuf>,
+    /// The lockfile pinning the hash of the spec.
+    pub lock: Option<PathBuf>,
+    /// Whether we are forbidden from downloading the spec or updating
+    /// the lockfile.  When set, the spec must already exist, and must
+    /// match the hash in the lockfile.
+    pub frozen: bool,
+}
+
+/// The contents of the spec lockfile.
+///
+/// This records where the spec came from, and the hash of the spec
+/// used for the last generation, so that two developers generating
+/// from the same commit generate the same client.
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SpecLock {
+    pub source: String,
+    pub sha256: String,
+}
+
+/// What checking the spec against the lockfile did to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockUpdate {
+    /// The spec matches the lockfile (or there is no lockfile to
+    /// check against).
+    Unchanged,
+    /// There was no lockfile, so one was written for the spec.
+    Created { sha256: String },
+    /// The spec no longer matches the lockfile, so the lockfile was
+    /// rewritten for it.
+    Changed { previous: String, sha256: String },
+    /// The spec matches the lockfile, but was loaded from a different
+    /// source than the one it records, so the lockfile was rewritten
+    /// with the new source.
+    SourceChanged { previous: String, source: String },
+}
+
+/// Loads the OpenAPI spec, downloading it if it does not exist.
+///
+/// The spec may be either JSON or YAML; see [`parse_document`].  The
+/// spec is checked against (or recorded into) the lockfile, if
+/// one is given.  Then, before the spec is deserialized, every patch
+/// in the patch directory is applied to it, in file name order.
+/// Files ending in `.merge.json` are treated as RFC 7396 JSON Merge
+/// Patches; every other `.json` file is treated as an RFC 6902 JSON
+/// Patch. This lets us correct the community spec when it drifts,
+/// without having to touch the generator itself.  A JSON Patch should
+/// `test` what it expects to find before changing it, so that it
+/// fails once upstream changes, rather than quietly applying.
+/// Finally, any overlays are merged into the spec.
+pub fn load(options: &LoadOptions) -> Result<openapiv3::OpenAPI, anyhow::Error> {
+    load_locked(options).map(|(api, _)| api)
+}
+
+/// Loads the OpenAPI spec, as [`load`] does; and reports whether the
+/// lockfile had to be written, so that a spec that has drifted from
+/// the lockfile doesn't go unnoticed.
+pub fn load_locked(
+    options: &LoadOptions,
+) -> Result<(openapiv3::OpenAPI, LockUpdate), anyhow::Error> {
+    let source = options.source.as_deref().unwrap_or(RAW_JSON_FILE);
+    let mut lock_update = LockUpdate::Unchanged;
+    let mut api = if options.path.as_deref() == Some(Path::new("-")) {
+        if options.frozen && options.lock.is_some() {
+            anyhow::bail!(
+                "the openapi file cannot be read from stdin in frozen mode, as it would not be \
+                 checked against the lockfile"
+            );
+        }
+        let mut contents = Vec::new();
+        std::io::Read::read_to_end(&mut std::io::stdin().lock(), &mut contents)
+            .context("when reading openapi file from stdin")?;
+        parse_document(&contents, None).context("when parsing openapi file from stdin")?
+    } else {
+        let path = attempt_download(options.path.as_deref(), source, options.frozen)?;
+        let contents = std::fs::read(&path).context("when opening openapi file")?;
+
+        if let Some(lock) = options.lock.as_deref() {
+            lock_update = check_lock(lock, source, &contents, options.frozen)?;
+        }
+
+        parse_document(&contents, Some(&path)).context("when parsing openapi file")?
+    };
+
+    if let Some(patches) = options.patches.as_deref() {
+        for patch in load_patches(patches)? {
+            patch.apply(&mut api)?;
+        }
+    }
+
+    if let Some(overlays) = options.overlays.as_deref() {
+        for overlay in crate::overlay::load_overlays(overlays)? {
+            overlay.apply(&mut api)?;
+        }
+    }
+
+    let api = serde_json::from_value(api).context("when deserializing openapi file")?;
+    Ok((api, lock_update))
+}
+
+/// Parses a JSON or YAML document.
+///
+/// The format is determined by the extension of `path`, if it has a
+/// `.json`, `.yaml` or `.yml` extension; otherwise, the contents are
+/// sniffed.  As YAML is a superset of JSON, we only need to look for
+/// the opening brace of a JSON object to tell the two apart.
+pub fn parse_document(
+    contents: &[u8],
+    path: Option<&Path>,
+) -> Result<serde_json::Value, anyhow::Error> {
+    let extension = path
+        .and_then(Path::extension)
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    let is_yaml = match extension.as_deref() {
+        Some("json") => false,
+        Some("yaml" | "yml") => true,
+        _ => contents
+            .iter()
+            .find(|c| !c.is_ascii_whitespace())
+            .is_some_and(|&c| c != b'{'),
+    };
+
+    if is_yaml {
+        Ok(serde_yaml::from_slice(contents)?)
+    } else {
+        Ok(serde_json::from_slice(contents)?)
+    }
+}
+
+fn check_lock(
+    lock: &Path,
+    source: &str,
+    contents: &[u8],
+    frozen: bool,
+) -> Result<LockUpdate, anyhow::Error> {
+    let current = SpecLock {
+        source: source.to_string(),
+        sha256: sha256(contents),
+    };
+    let previous = match std::fs::read(lock) {
+        Ok(previous) => Some(
+            serde_json::from_slice::<SpecLock>(&previous)
+                .with_context(|| format!("when parsing the lockfile {}", lock.display()))?,
+        ),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(e).with_context(|| format!("when reading the lockfile {}", lock.display()))
+        }
+    };
+
+    let update = match previous {
+        Some(previous) if previous == current => return Ok(LockUpdate::Unchanged),
+        Some(previous) if frozen && previous.sha256 == current.sha256 => anyhow::bail!(
+            "the openapi file was loaded from {}, but the lockfile {} records {}; refusing to \
+             continue in frozen mode",
+            current.source,
+            lock.display(),
+            previous.source
+        ),
+        Some(previous) if frozen => anyhow::bail!(
+            "the openapi file has the hash {}, but the lockfile {} expects {}; refusing to \
+             continue in frozen mode",
+            current.sha256,
+            lock.display(),
+            previous.sha256
+        ),
+        None if frozen => anyhow::bail!(
+            "the lockfile {} does not exist; refusing to continue in frozen mode",
+            lock.display()
+        ),
+        Some(previous) if previous.sha256 == current.sha256 => LockUpdate::SourceChanged {
+            previous: previous.source,
+            source: current.source.clone(),
+        },
+        Some(previous) => LockUpdate::Changed {
+            previous: previous.sha256,
+            sha256: current.sha256.clone(),
+        },
+        None => LockUpdate::Created {
+            sha256: current.sha256.clone(),
+        },
+    };
+    write_json(lock, &current)
+        .with_context(|| format!("when writing the lockfile {}", lock.display()))?;
+    Ok(update)
+}
+
+/// Where, and how, to update the cached spec from.
+#[derive(Debug)]
+pub struct UpdateOptions {
+    /// The URL to download the spec from.
+    pub source: String,
+    /// The directory to cache the spec, and its HTTP metadata, in.
+    pub cache: PathBuf,
+    /// Where to copy the updated spec to, for generation.
+    pub path: PathBuf,
+    /// The lockfile to record the updated spec into.
+    pub lock: Option<PathBuf>,
+}
+
+/// The result of updating the spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecUpdate {
+    /// The upstream spec has not changed since we last downloaded it.
+    Unchanged,
+    /// The upstream spec has changed, and the cache was updated.
+    Changed,
+}
+
+/// The HTTP metadata of the cached spec, so that we can make
+/// conditional requests for it.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct CacheMetadata {
+    source: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// Refreshes the cached spec from upstream.
+///
+/// This sends a conditional request, using the `ETag` and
+/// `Last-Modified` headers from the last download, so that we only
+/// download the spec when it has actually changed.  Either way, the
+/// cached spec is then copied to the spec path, and recorded into the
+/// lockfile.
+pub fn update(options: &UpdateOptions) -> Result<SpecUpdate, anyhow::Error> {
+    std::fs::create_dir_all(&options.cache).with_context(|| {
+        format!(
+            "when creating the cache directory {}",
+            options.cache.display()
+        )
+    })?;
+    let spec_path = options.cache.join("openapi.json");
+    let meta_path = options.cache.join("openapi.meta.json");
+
+    // only trust the metadata if it is for the same source, and we
+    // still have the spec it describes.
+    let meta = std::fs::read(&meta_path)
+        .ok()
+        .and_then(|meta| serde_json::from_slice::<CacheMetadata>(&meta).ok())
+        .filter(|meta| meta.source == options.source && spec_path.exists());
+    let previous = meta.as_ref().and_then(|_| std::fs::read(&spec_path).ok());
+
+    let mut request = ureq::get(&options.source);
+    if let Some(meta) = meta.as_ref() {
+        if let Some(etag) = meta.etag.as_deref() {
+            request = request.set("If-None-Match", etag);
+        }
+        if let Some(last_modified) = meta.last_modified.as_deref() {
+            request = request.set("If-Modified-Since", last_modified);
+        }
+    }
+    let response = request
+        .call()
+        .context("when attempting to download openapi file")?;
+
+    let (contents, update) = match (response.status(), previous) {
+        (304, Some(previous)) => (previous, SpecUpdate::Unchanged),
+        (200, previous) => {
+            let meta = CacheMetadata {
+                source: options.source.clone(),
+                etag: response.header("ETag").map(str::to_string),
+                last_modified: response.header("Last-Modified").map(str::to_string),
+            };
+            let mut contents = Vec::new();
+            std::io::copy(&mut response.into_reader(), &mut contents)
+                .context("when reading openapi file")?;
+            let update = if previous.as_deref() == Some(&contents[..]) {
+                SpecUpdate::Unchanged
+            } else {
+                SpecUpdate::Changed
+            };
+            std::fs::write(&spec_path, &contents)
+                .with_context(|| format!("when writing {}", spec_path.display()))?;
+            write_json(&meta_path, &meta)
+                .with_context(|| format!("when writing {}", meta_path.display()))?;
+            (contents, update)
+        }
+        (status, _) => anyhow::bail!("Failed to download openapi file: {status}"),
+    };
+
+    std::fs::write(&options.path, &contents)
+        .with_context(|| format!("when writing {}", options.path.display()))?;
+    if let Some(lock) = options.lock.as_deref() {
+        check_lock(lock, &options.source, &contents, false)?;
+    }
+
+    Ok(update)
+}
+
+fn sha256(contents: &[u8]) -> String { format!("{:x}", sha2::Sha256::digest(contents)) }
+
+fn write_json<T: serde::Serialize>(path: &Path, value: &T) -> Result<(), anyhow::Error> {
+    let mut out = serde_json::to_string_pretty(value)?;
+    out.push('\n');
+    std::fs::write(path, out)?;
+    Ok(())
+}
+
+/// A single patch file, applied to the raw JSON of the spec.
+#[derive(Debug)]
+struct SpecPatch {
+    path: PathBuf,
+    kind: SpecPatchKind,
+}
+
+#[derive(Debug)]
+enum SpecPatchKind {
+    /// An RFC 6902 JSON Patch.
+    Json(json_patch::Patch),
+    /// An RFC 7396 JSON Merge Patch.
+    Merge(serde_json::Value),
+}
+
+impl SpecPatch {
+    fn apply(&self, api: &mut serde_json::Value) -> Result<(), anyhow::Error> {
+        match &self.kind {
+            SpecPatchKind::Json(patch) => json_patch::patch(api, patch).with_context(|| {
+                format!(
+                    "when applying the patch {}; the upstream spec may have changed, so the patch \
+                     needs to be updated or removed",
+                    self.path.display()
+                )
+            }),
+            SpecPatchKind::Merge(patch) => {
+                json_patch::merge(api, patch);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn load_patches(dir: &Path) -> Result<Vec<SpecPatch>, anyhow::Error> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("when reading the patch directory {}", dir.display()))?;
+    let mut paths = entries
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("when reading the patch directory {}", dir.display()))?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let contents = std::fs::read(&path)
+                .with_context(|| format!("when reading the patch {}", path.display()))?;
+            let is_merge = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(".merge.json"));
+            let kind = if is_merge {
+                serde_json::from_slice(&contents).map(SpecPatchKind::Merge)
+            } else {
+                serde_json::from_slice(&contents).map(SpecPatchKind::Json)
+            };
+            let kind =
+                kind.with_context(|| format!("when parsing the patch {}", path.display()))?;
+            Ok(SpecPatch { path, kind })
+        })
+        .collect()
+}
+
+fn attempt_download(
+    path: Option<&Path>,
+    source: &str,
+    frozen: bool,
+) -> Result<PathBuf, anyhow::Error> {
+    let path = path.map_or_else(|| PathBuf::from("./openapi.json"), Path::to_path_buf);
     if !path.exists() {
-        let response = ureq::get(RAW_JSON_FILE)
+        if frozen {
+            anyhow::bail!(
+                "the openapi file {} does not exist, and we may not download it in frozen mode",
+                path.display()
+            );
+        }
+        let response = ureq::get(source)
             .call()
             .context("when attempting to download openapi file")?;
         if response.status() != 200 {
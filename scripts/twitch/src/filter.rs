@@ -0,0 +1,120 @@
+//! Include/exclude rules for trimming the generated client down to
+//! the parts of the API we actually need.
+
+use crate::extract::{Extract, ExtractNamespaceMethod};
+use anyhow::Context as _;
+use heck::ToLowerCamelCase as _;
+
+/// Which parts of the API to generate.
+///
+/// A method is generated if it is included, and not excluded.  It is
+/// included if no include rules are given at all, or if any of them
+/// match: its namespace, its operationId, or its path.  It is
+/// excluded if any of the exclude rules match.  Namespaces are
+/// generated as long as they still have methods left (or never had
+/// any to begin with, and are not filtered out by name).
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Filters {
+    pub include: FilterRules,
+    pub exclude: FilterRules,
+}
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FilterRules {
+    /// Namespace names, e.g. `channelPoints` or `Channel Points`.
+    pub namespaces: Vec<String>,
+    /// Operation IDs, e.g. `get-users` or `getUsers`.
+    pub operations: Vec<String>,
+    /// Globs matched against the operation path, e.g.
+    /// `/moderation/*`.  A `*` does not match across a `/`, but `**`
+    /// does.
+    pub paths: Vec<String>,
+}
+
+impl FilterRules {
+    fn is_empty(&self) -> bool {
+        self.namespaces.is_empty() && self.operations.is_empty() && self.paths.is_empty()
+    }
+}
+
+/// A compiled set of [`FilterRules`].
+#[derive(Debug)]
+struct Matcher {
+    namespaces: Vec<String>,
+    operations: Vec<String>,
+    paths: globset::GlobSet,
+}
+
+impl Matcher {
+    fn new(rules: &FilterRules) -> Result<Self, anyhow::Error> {
+        let mut paths = globset::GlobSetBuilder::new();
+        for path in &rules.paths {
+            let glob = globset::GlobBuilder::new(path)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("when parsing the path glob `{path}`"))?;
+            paths.add(glob);
+        }
+
+        Ok(Self {
+            namespaces: normalize(&rules.namespaces),
+            operations: normalize(&rules.operations),
+            paths: paths.build()?,
+        })
+    }
+
+    fn matches_namespace(&self, name: &str) -> bool { self.namespaces.iter().any(|n| n == name) }
+
+    fn matches_method(&self, namespace: &str, method: &ExtractNamespaceMethod<'_>) -> bool {
+        self.matches_namespace(namespace)
+            || self
+                .operations
+                .iter()
+                .any(|op| *op == method.name || op == method.operation_id)
+            || self.paths.is_match(method.path)
+    }
+}
+
+impl Filters {
+    /// Checks that all the path globs are valid.
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        Matcher::new(&self.include).context("in `include`")?;
+        Matcher::new(&self.exclude).context("in `exclude`")?;
+        Ok(())
+    }
+
+    /// Removes everything from the extracted API that these filters
+    /// do not allow.
+    pub fn apply(&self, extract: &mut Extract<'_>) -> Result<(), anyhow::Error> {
+        let include = Matcher::new(&self.include).context("when compiling the include rules")?;
+        let exclude = Matcher::new(&self.exclude).context("when compiling the exclude rules")?;
+        let include_all = self.include.is_empty();
+
+        extract.namespaces.retain(|name, namespace| {
+            if namespace.methods.is_empty() {
+                return (include_all || include.matches_namespace(name))
+                    && !exclude.matches_namespace(name);
+            }
+
+            namespace.methods.retain(|method| {
+                (include_all || include.matches_method(name, method))
+                    && !exclude.matches_method(name, method)
+            });
+            !namespace.methods.is_empty()
+        });
+
+        Ok(())
+    }
+}
+
+/// Normalizes namespace and operation names to the form we use for
+/// them in the generated client, so that either form can be used in
+/// filters.
+fn normalize(names: &[String]) -> Vec<String> {
+    names
+        .iter()
+        .flat_map(|name| [name.clone(), name.to_lower_camel_case()])
+        .collect()
+}
@@ -0,0 +1,919 @@
+//! The intermediate representation of the API that the client is
+//! generated from.
+//!
+//! [`crate::extract::Extract`] decides what the API looks like, but
+//! it borrows from the OpenAPI spec, and leaves every type as the
+//! spec has it (`$ref`s and all).  This is the owned, normalized
+//! version of it: every reference is resolved, and every type is one
+//! we know how to generate.  The renderers only ever look at this;
+//! and, as it can be serialized (see the `dump-ir` command), neither
+//! does anything else that wants to know what the client looks like.
+
+use crate::config::{Config, FieldSegment};
+use crate::extract::{Extract, ExtractNamespaceMethod, ExtractNamespaceMethodParam, ParamKind};
+use anyhow::Context as _;
+use heck::ToLowerCamelCase as _;
+use indexmap::{IndexMap, IndexSet};
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Api {
+    /// The namespaces, in spec order; see
+    /// [`crate::extract::Extract::namespaces`].
+    pub namespaces: IndexMap<String, Namespace>,
+    /// The named schemas that the methods reference, keyed by their
+    /// name in the spec.  Every schema comes after the schemas it
+    /// references.
+    pub schemas: IndexMap<String, Schema>,
+    /// The OAuth scopes, with their descriptions: every scope the
+    /// spec's security schemes declare, in spec order, followed by
+    /// any the methods require that the spec doesn't declare
+    /// (which have no description).
+    pub scopes: IndexMap<String, Option<String>>,
+    /// The kinds of IDs that are given a type of their own, with
+    /// their descriptions, in the order the configuration has them;
+    /// see [`Type::Id`].
+    pub ids: IndexMap<String, Option<String>>,
+}
+
+/// A type that the spec gives a name to.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Schema {
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub ty: Type,
+    /// Whether the schema references itself, directly or through
+    /// other schemas; see [`sort_schemas`].
+    pub recursive: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Namespace {
+    pub methods: Vec<Method>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Method {
+    /// The name of the generated method.
+    pub name: String,
+    pub operation_id: String,
+    pub path: String,
+    /// The HTTP method, in lowercase.
+    pub method: String,
+    pub description: Option<String>,
+    /// The parameters, in spec order.
+    pub parameters: Vec<Param>,
+    /// The JSON response body, if there is one.
+    pub response: Option<Type>,
+    /// The status codes the method may respond with on success, in
+    /// order; empty if the spec doesn't say.
+    pub statuses: Vec<u16>,
+    /// Whether some of the statuses have no body (say, a 204), so
+    /// that there may be no response, even though there is a type
+    /// for it.
+    pub may_be_empty: bool,
+    /// Whether the response is a file to download, rather than JSON.
+    pub download: bool,
+    /// The error responses the spec documents, in status order.
+    pub errors: Vec<ErrorResponse>,
+    /// The sets of scopes the method requires, any one of which is
+    /// enough; empty if it requires none.
+    pub requires_scopes: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ErrorResponse {
+    pub status: u16,
+    /// What the status means for the method.
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Param {
+    /// The name of the parameter in the generated client.
+    pub id: String,
+    /// The name of the parameter in the request.
+    pub name: String,
+    pub description: Option<String>,
+    pub optional: bool,
+    pub kind: ParamKind,
+    #[serde(rename = "type")]
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Type {
+    String {
+        /// The allowed values, if the string is an enum.
+        #[serde(rename = "enum", default, skip_serializing_if = "Vec::is_empty")]
+        enumeration: Vec<String>,
+    },
+    Number,
+    Integer,
+    Boolean,
+    Array {
+        items: Box<Type>,
+    },
+    Object(ObjectType),
+    /// Any one of the variants (`oneOf`, or `anyOf`).
+    Union {
+        variants: Vec<Type>,
+        /// The property whose value tells the variants apart, if the
+        /// spec names one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        discriminator: Option<String>,
+    },
+    /// All of the parts at once (`allOf`).
+    Intersection {
+        parts: Vec<Type>,
+    },
+    /// A string that is an ID of the named kind; one of the
+    /// [`Api::ids`].
+    Id {
+        name: String,
+    },
+    /// A reference to one of the [`Api::schemas`], by name.
+    Ref {
+        name: String,
+    },
+    /// The inner type, or `null`.
+    Nullable {
+        inner: Box<Type>,
+    },
+    /// Anything we can't say more about; e.g., an array without an
+    /// item type.
+    Unknown,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectType {
+    /// The properties, in the order the spec has them.
+    pub properties: Vec<Property>,
+    pub additional_properties: Option<AdditionalProperties>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Property {
+    /// The name of the property in the generated client.
+    pub id: String,
+    /// The name of the property on the wire.
+    pub name: String,
+    pub description: Option<String>,
+    pub optional: bool,
+    #[serde(rename = "type")]
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum AdditionalProperties {
+    /// Any additional property is allowed, whatever its type.
+    Any,
+    /// Additional properties are allowed, but must be of this type.
+    Typed {
+        description: Option<String>,
+        #[serde(rename = "type")]
+        ty: Box<Type>,
+    },
+}
+
+impl Api {
+    /// Builds the representation from the extracted API, resolving
+    /// every type against the spec.
+    ///
+    /// Types that the spec names in `components/schemas` are not
+    /// inlined, but referenced by name (see [`Type::Ref`]), and
+    /// collected into [`Api::schemas`]; unless they are the request
+    /// or response body of a method itself, which is always
+    /// inlined.
+    ///
+    /// Fields that the configuration marks as nullable are made so
+    /// here, after the fact; see [`Api::mark_nullable`].
+    pub fn new(
+        extract: &Extract<'_>,
+        api: &openapiv3::OpenAPI,
+        config: &Config,
+    ) -> Result<Self, anyhow::Error> {
+        let mut builder = Builder {
+            api,
+            config,
+            referenced: IndexSet::new(),
+        };
+
+        let mut namespaces = IndexMap::new();
+        for (name, namespace) in &extract.namespaces {
+            let methods = namespace
+                .methods
+                .iter()
+                .map(|method| {
+                    builder
+                        .method(method)
+                        .with_context(|| format!("in the method `{name}.{}`", method.name))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            namespaces.insert(name.clone(), Namespace { methods });
+        }
+
+        // building a schema may reference yet more schemas, which are
+        // added to the end of the set as we go.
+        let mut schemas = IndexMap::new();
+        let mut index = 0;
+        while let Some(name) = builder.referenced.get_index(index).cloned() {
+            let schema = builder
+                .named_schema(&name)
+                .with_context(|| format!("in the schema `{name}`"))?;
+            schemas.insert(name, schema);
+            index += 1;
+        }
+
+        let mut scopes = scope_catalog(api)?;
+        let required = namespaces
+            .values()
+            .flat_map(|namespace| &namespace.methods)
+            .flat_map(|method| method.requires_scopes.iter().flatten());
+        for scope in required {
+            if !scopes.contains_key(scope) {
+                scopes.insert(scope.clone(), None);
+            }
+        }
+
+        let ids = config
+            .ids
+            .iter()
+            .map(|id| (id.name.clone(), id.description.clone()))
+            .collect();
+
+        let mut this = Self {
+            namespaces,
+            schemas: sort_schemas(schemas),
+            scopes,
+            ids,
+        };
+        this.mark_nullable(extract, config, api)?;
+        Ok(this)
+    }
+
+    /// Applies the `nullable` overrides in the configuration, for
+    /// the fields the spec forgets to mark.
+    ///
+    /// The overrides for a schema also apply to the request and
+    /// response bodies that are inlined from it.  Overrides for
+    /// operations we don't generate, or for schemas nothing
+    /// references, are ignored; but a path that doesn't lead
+    /// anywhere is an error, as is one that leads into a named
+    /// schema (which has its own overrides, as it is shared).
+    fn mark_nullable(
+        &mut self,
+        extract: &Extract<'_>,
+        config: &Config,
+        api: &openapiv3::OpenAPI,
+    ) -> Result<(), anyhow::Error> {
+        for (id, overrides) in &config.operations {
+            for path in &overrides.nullable {
+                let methods = self
+                    .namespaces
+                    .values_mut()
+                    .flat_map(|namespace| &mut namespace.methods)
+                    .filter(|method| method.operation_id == *id);
+                for method in methods {
+                    mark_method_nullable(method, path.segments()).with_context(|| {
+                        format!("when applying `operations.{id}.nullable` entry `{path}`")
+                    })?;
+                }
+            }
+        }
+
+        for (name, overrides) in &config.schemas {
+            // the methods are in the same order as they were
+            // extracted in.
+            let methods = extract
+                .namespaces
+                .iter()
+                .zip(self.namespaces.values_mut())
+                .flat_map(|((_, extracted), namespace)| {
+                    extracted.methods.iter().zip(&mut namespace.methods)
+                });
+            for (extracted, method) in methods {
+                for path in &overrides.nullable {
+                    let context = || {
+                        format!(
+                            "when applying `schemas.{name}.nullable` entry `{path}` to the method \
+                             `{}`",
+                            extracted.name
+                        )
+                    };
+                    if extracted.body_schema == Some(name.as_str()) {
+                        mark_body_nullable(method, path.segments()).with_context(context)?;
+                    }
+                    let response = method
+                        .response
+                        .as_mut()
+                        .filter(|_| extracted.ret_schema == Some(name.as_str()));
+                    if let Some(response) = response {
+                        mark_nullable(response, path.segments()).with_context(context)?;
+                    }
+                }
+            }
+
+            match self.schemas.get_mut(name) {
+                Some(schema) => {
+                    for path in &overrides.nullable {
+                        mark_nullable(&mut schema.ty, path.segments()).with_context(|| {
+                            format!("when applying `schemas.{name}.nullable` entry `{path}`")
+                        })?;
+                    }
+                }
+                None if api
+                    .components
+                    .as_ref()
+                    .is_some_and(|components| components.schemas.contains_key(name)) => {}
+                None => anyhow::bail!(
+                    "the config has an override for the schema `{name}`, but the spec has no such \
+                     schema"
+                ),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Param {
+    /// Checks if the caller may leave the parameter out; either the
+    /// spec says so, or we fill it in with the token's user.
+    #[must_use]
+    pub fn is_optional(&self) -> bool { self.optional || self.kind.is_current_user() }
+}
+
+impl Method {
+    #[must_use]
+    pub fn has_params(&self) -> bool { !self.parameters.is_empty() }
+
+    /// Checks if the caller may leave out every parameter, and so
+    /// the request itself.
+    #[must_use]
+    pub fn has_only_optional_params(&self) -> bool {
+        self.parameters.iter().all(Param::is_optional)
+    }
+
+    /// Checks if any of the parameters are sent in the body.
+    #[must_use]
+    pub fn has_body(&self) -> bool { self.body_params().next().is_some() }
+
+    pub fn body_params(&self) -> impl Iterator<Item = &Param> {
+        self.parameters.iter().filter(|param| {
+            matches!(
+                param.kind,
+                ParamKind::Body | ParamKind::BodyCurrentUserToken | ParamKind::BodyUserToken
+            )
+        })
+    }
+
+    pub fn path_params(&self) -> impl Iterator<Item = &Param> {
+        self.parameters
+            .iter()
+            .filter(|param| param.kind == ParamKind::Path)
+    }
+
+    pub fn header_params(&self) -> impl Iterator<Item = &Param> {
+        self.parameters
+            .iter()
+            .filter(|param| param.kind == ParamKind::Header)
+    }
+
+    pub fn query_params(&self) -> impl Iterator<Item = &Param> {
+        self.parameters.iter().filter(|param| {
+            matches!(
+                param.kind,
+                ParamKind::Query | ParamKind::QueryCurrentUserToken | ParamKind::QueryUserToken
+            )
+        })
+    }
+}
+
+impl Type {
+    /// Collects the names of the schemas this type references,
+    /// directly.
+    pub fn refs<'a>(&'a self, out: &mut IndexSet<&'a str>) {
+        self.visit(&mut |ty| {
+            if let Type::Ref { name } = ty {
+                out.insert(name);
+            }
+        });
+    }
+
+    /// Collects the kinds of IDs this type uses, directly.
+    pub fn ids<'a>(&'a self, out: &mut IndexSet<&'a str>) {
+        self.visit(&mut |ty| {
+            if let Type::Id { name } = ty {
+                out.insert(name);
+            }
+        });
+    }
+
+    /// Calls `f` with this type, and every type inside of it; but
+    /// not past a reference.
+    fn visit<'a>(&'a self, f: &mut impl FnMut(&'a Type)) {
+        f(self);
+        match self {
+            Type::Array { items: ty } | Type::Nullable { inner: ty } => ty.visit(f),
+            Type::Object(obj) => obj.visit(f),
+            Type::Union {
+                variants: types, ..
+            }
+            | Type::Intersection { parts: types } => {
+                for ty in types {
+                    ty.visit(f);
+                }
+            }
+            Type::String { .. }
+            | Type::Number
+            | Type::Integer
+            | Type::Boolean
+            | Type::Id { .. }
+            | Type::Ref { .. }
+            | Type::Unknown => {}
+        }
+    }
+
+    /// Whether `null` is a valid value of this type.
+    #[must_use]
+    pub fn is_nullable(&self) -> bool { matches!(self, Type::Nullable { .. }) }
+
+    /// The type, without the `null`.
+    #[must_use]
+    pub fn non_null(&self) -> &Type {
+        match self {
+            Type::Nullable { inner } => inner,
+            ty => ty,
+        }
+    }
+
+    /// Makes the type nullable, if it isn't already.
+    pub fn make_nullable(&mut self) {
+        if !self.is_nullable() {
+            let inner = std::mem::replace(self, Type::Unknown);
+            *self = Type::Nullable {
+                inner: Box::new(inner),
+            };
+        }
+    }
+}
+
+impl ObjectType {
+    /// Collects the names of the schemas this type references,
+    /// directly.
+    pub fn refs<'a>(&'a self, out: &mut IndexSet<&'a str>) {
+        self.visit(&mut |ty| {
+            if let Type::Ref { name } = ty {
+                out.insert(name);
+            }
+        });
+    }
+
+    fn visit<'a>(&'a self, f: &mut impl FnMut(&'a Type)) {
+        for prop in &self.properties {
+            prop.ty.visit(f);
+        }
+        if let Some(AdditionalProperties::Typed { ty, .. }) = &self.additional_properties {
+            ty.visit(f);
+        }
+    }
+}
+
+/// Converts the spec's types into ours, keeping track of every named
+/// schema we reference along the way.
+struct Builder<'a> {
+    api: &'a openapiv3::OpenAPI,
+    config: &'a Config,
+    referenced: IndexSet<String>,
+}
+
+impl Builder<'_> {
+    fn method(&mut self, method: &ExtractNamespaceMethod<'_>) -> Result<Method, anyhow::Error> {
+        Ok(Method {
+            name: method.name.clone(),
+            operation_id: method.operation_id.to_string(),
+            path: method.path.to_string(),
+            method: method.method.to_string(),
+            description: method.description.map(str::to_string),
+            parameters: method
+                .parameters
+                .values()
+                .map(|param| {
+                    self.param(param)
+                        .with_context(|| format!("in the parameter `{}`", param.name))
+                })
+                .collect::<Result<_, _>>()?,
+            response: method
+                .ret
+                .map(|ret| self.schema(ret))
+                .transpose()
+                .context("in the response")?,
+            statuses: method.statuses.clone(),
+            may_be_empty: method.may_be_empty,
+            download: method.download,
+            errors: method
+                .errors
+                .iter()
+                .map(|error| ErrorResponse {
+                    status: error.status,
+                    description: error.description.to_string(),
+                })
+                .collect(),
+            requires_scopes: method
+                .requires_scopes
+                .iter()
+                .map(|set| set.iter().map(|scope| scope.to_string()).collect())
+                .collect(),
+        })
+    }
+
+    fn param(&mut self, param: &ExtractNamespaceMethodParam<'_>) -> Result<Param, anyhow::Error> {
+        let mut ty = self.schema(param.ty)?;
+        self.brand(&param.name, &mut ty);
+        Ok(Param {
+            id: param.id.clone(),
+            name: param.name.clone(),
+            description: param.description.map(str::to_string),
+            optional: param.optional,
+            kind: param.kind,
+            ty,
+        })
+    }
+
+    /// Types the field (or parameter) as an ID, if the configuration
+    /// says it is one.
+    fn brand(&self, field: &str, ty: &mut Type) {
+        if let Some(id) = self.config.id_type(field) {
+            brand(ty, &id.name);
+        }
+    }
+
+    fn named_schema(&mut self, name: &str) -> Result<Schema, anyhow::Error> {
+        let schema = self
+            .api
+            .components
+            .as_ref()
+            .and_then(|components| components.schemas.get(name))
+            .context("there is no such schema")?;
+        let (description, ty) = match schema {
+            // an alias for another schema.
+            openapiv3::ReferenceOr::Reference { reference } => (None, self.reference(reference)?),
+            openapiv3::ReferenceOr::Item(schema) => {
+                (schema.schema_data.description.clone(), self.schema(schema)?)
+            }
+        };
+        Ok(Schema {
+            description,
+            ty,
+            recursive: false,
+        })
+    }
+
+    /// Converts a schema that may be a reference, returning its
+    /// description along with it.
+    fn maybe_ref<S>(
+        &mut self,
+        schema: &openapiv3::ReferenceOr<S>,
+    ) -> Result<(Option<String>, Type), anyhow::Error>
+    where
+        S: std::borrow::Borrow<openapiv3::Schema>,
+    {
+        match schema {
+            openapiv3::ReferenceOr::Item(schema) => {
+                let schema: &openapiv3::Schema = schema.borrow();
+                Ok((schema.schema_data.description.clone(), self.schema(schema)?))
+            }
+            openapiv3::ReferenceOr::Reference { reference } => {
+                let description = reference
+                    .strip_prefix("#/components/schemas/")
+                    .and_then(|name| {
+                        self.api
+                            .components
+                            .as_ref()
+                            .and_then(|components| components.schemas.get(name))
+                    })
+                    .and_then(|schema| crate::extract::resolve_reference_schema(schema, self.api))
+                    .and_then(|schema| schema.schema_data.description.clone());
+                Ok((description, self.reference(reference)?))
+            }
+        }
+    }
+
+    fn reference(&mut self, reference: &str) -> Result<Type, anyhow::Error> {
+        let name = reference
+            .strip_prefix("#/components/schemas/")
+            .with_context(|| format!("the reference `{reference}` is not to a schema"))?;
+        self.referenced.insert(name.to_string());
+        Ok(Type::Ref {
+            name: name.to_string(),
+        })
+    }
+
+    fn ty(&mut self, ty: &openapiv3::Type) -> Result<Type, anyhow::Error> {
+        Ok(match ty {
+            openapiv3::Type::String(s) => Type::String {
+                enumeration: s.enumeration.iter().flatten().cloned().collect(),
+            },
+            openapiv3::Type::Number(_) => Type::Number,
+            openapiv3::Type::Integer(_) => Type::Integer,
+            openapiv3::Type::Boolean(_) => Type::Boolean,
+            openapiv3::Type::Array(array) => {
+                let items = match array.items.as_ref() {
+                    Some(item) => self.maybe_ref(item).context("in the array items")?.1,
+                    None => Type::Unknown,
+                };
+                Type::Array {
+                    items: Box::new(items),
+                }
+            }
+            openapiv3::Type::Object(obj) => Type::Object(self.object(obj)?),
+        })
+    }
+
+    fn schema(&mut self, schema: &openapiv3::Schema) -> Result<Type, anyhow::Error> {
+        let mut ty = self.schema_kind(schema)?;
+        if schema.schema_data.nullable {
+            ty.make_nullable();
+        }
+        Ok(ty)
+    }
+
+    fn schema_kind(&mut self, schema: &openapiv3::Schema) -> Result<Type, anyhow::Error> {
+        match &schema.schema_kind {
+            openapiv3::SchemaKind::Type(ty) => self.ty(ty),
+            openapiv3::SchemaKind::OneOf { one_of: variants }
+            | openapiv3::SchemaKind::AnyOf { any_of: variants } => Ok(Type::Union {
+                variants: self.composed(variants).context("in a variant")?,
+                discriminator: schema
+                    .schema_data
+                    .discriminator
+                    .as_ref()
+                    .map(|discriminator| discriminator.property_name.clone()),
+            }),
+            openapiv3::SchemaKind::AllOf { all_of } => Ok(Type::Intersection {
+                parts: self.composed(all_of).context("in a part")?,
+            }),
+            other => anyhow::bail!("the schema is not a supported type (saw {other:?})"),
+        }
+    }
+
+    fn composed(
+        &mut self,
+        schemas: &[openapiv3::ReferenceOr<openapiv3::Schema>],
+    ) -> Result<Vec<Type>, anyhow::Error> {
+        schemas
+            .iter()
+            .map(|schema| Ok(self.maybe_ref(schema)?.1))
+            .collect()
+    }
+
+    fn object(&mut self, obj: &openapiv3::ObjectType) -> Result<ObjectType, anyhow::Error> {
+        let properties = obj
+            .properties
+            .iter()
+            .map(|(name, prop)| {
+                let (description, mut ty) = self
+                    .maybe_ref(prop)
+                    .with_context(|| format!("in the property `{name}`"))?;
+                self.brand(name, &mut ty);
+                Ok(Property {
+                    id: name.to_lower_camel_case(),
+                    name: name.clone(),
+                    description,
+                    optional: !obj.required.contains(name),
+                    ty,
+                })
+            })
+            .collect::<Result<_, anyhow::Error>>()?;
+
+        let additional_properties = match obj.additional_properties.as_ref() {
+            Some(openapiv3::AdditionalProperties::Any(false)) | None => None,
+            Some(openapiv3::AdditionalProperties::Any(true)) => Some(AdditionalProperties::Any),
+            Some(openapiv3::AdditionalProperties::Schema(schema)) => {
+                let (description, ty) = self
+                    .maybe_ref(schema)
+                    .context("in the additional properties")?;
+                Some(AdditionalProperties::Typed {
+                    description,
+                    ty: Box::new(ty),
+                })
+            }
+        };
+
+        Ok(ObjectType {
+            properties,
+            additional_properties,
+        })
+    }
+}
+
+/// Collects the scopes of every OAuth2 flow of every security scheme
+/// in the spec.
+fn scope_catalog(
+    api: &openapiv3::OpenAPI,
+) -> Result<IndexMap<String, Option<String>>, anyhow::Error> {
+    let mut catalog = IndexMap::new();
+    let schemes = api
+        .components
+        .iter()
+        .flat_map(|components| &components.security_schemes);
+    for (name, scheme) in schemes {
+        let openapiv3::ReferenceOr::Item(openapiv3::SecurityScheme::OAuth2 { flows, .. }) = scheme
+        else {
+            continue;
+        };
+        // the scopes of each flow aren't public, so we have to look
+        // at them the long way around.
+        let flows = serde_json::to_value(flows)
+            .with_context(|| format!("when reading the security scheme `{name}`"))?;
+        let flows = flows
+            .as_object()
+            .into_iter()
+            .flat_map(|flows| flows.values());
+        for flow in flows {
+            let scopes = flow
+                .get("scopes")
+                .and_then(|scopes| scopes.as_object())
+                .into_iter()
+                .flatten();
+            for (scope, description) in scopes {
+                catalog.entry(scope.clone()).or_insert_with(|| {
+                    description
+                        .as_str()
+                        .filter(|description| !description.is_empty())
+                        .map(str::to_string)
+                });
+            }
+        }
+    }
+    Ok(catalog)
+}
+
+fn mark_method_nullable(method: &mut Method, path: &[FieldSegment]) -> Result<(), anyhow::Error> {
+    let Some((FieldSegment::Field(first), rest)) = path.split_first() else {
+        unreachable!("a field path always starts with a field")
+    };
+    if first == "response" {
+        let response = method
+            .response
+            .as_mut()
+            .context("the operation has no response body")?;
+        return mark_nullable(response, rest);
+    }
+    let param = method
+        .parameters
+        .iter_mut()
+        .find(|param| param.name == *first)
+        .with_context(|| format!("the operation has no parameter named `{first}`"))?;
+    mark_nullable(&mut param.ty, rest)
+}
+
+/// Makes the field at the path nullable, in a request body that was
+/// split up into parameters; so the first part of the path is the
+/// name of a body parameter.
+fn mark_body_nullable(method: &mut Method, path: &[FieldSegment]) -> Result<(), anyhow::Error> {
+    let Some((FieldSegment::Field(first), rest)) = path.split_first() else {
+        unreachable!("a field path always starts with a field")
+    };
+    let param = method
+        .parameters
+        .iter_mut()
+        .filter(|param| {
+            matches!(
+                param.kind,
+                ParamKind::Body | ParamKind::BodyCurrentUserToken | ParamKind::BodyUserToken
+            )
+        })
+        .find(|param| param.name == *first)
+        .with_context(|| format!("there is no field named `{first}`"))?;
+    mark_nullable(&mut param.ty, rest)
+}
+
+/// Makes the plain strings in the type IDs of the named kind; through
+/// arrays and `null`, but nothing else.  An enum is never an ID.
+fn brand(ty: &mut Type, name: &str) {
+    match ty {
+        Type::Array { items: ty } | Type::Nullable { inner: ty } => brand(ty, name),
+        Type::String { enumeration } if enumeration.is_empty() => {
+            *ty = Type::Id {
+                name: name.to_string(),
+            };
+        }
+        _ => {}
+    }
+}
+
+/// Makes the field at the path nullable.  Every variant of a union
+/// (or part of an intersection) that has the field gets it.
+fn mark_nullable(ty: &mut Type, path: &[FieldSegment]) -> Result<(), anyhow::Error> {
+    let Some((segment, rest)) = path.split_first() else {
+        ty.make_nullable();
+        return Ok(());
+    };
+    match (ty, segment) {
+        (Type::Nullable { inner }, _) => mark_nullable(inner, path),
+        (Type::Array { items }, FieldSegment::Items) => mark_nullable(items, rest),
+        (Type::Object(obj), FieldSegment::Field(name)) => {
+            let prop = obj
+                .properties
+                .iter_mut()
+                .find(|prop| prop.name == *name)
+                .with_context(|| format!("there is no field named `{name}`"))?;
+            mark_nullable(&mut prop.ty, rest)
+        }
+        (
+            Type::Union {
+                variants: types, ..
+            }
+            | Type::Intersection { parts: types },
+            _,
+        ) => {
+            let mut first_error = None;
+            let mut marked = false;
+            for ty in types {
+                match mark_nullable(ty, path) {
+                    Ok(()) => marked = true,
+                    Err(error) => {
+                        first_error.get_or_insert(error);
+                    }
+                }
+            }
+            match first_error {
+                Some(error) if !marked => Err(error),
+                _ => Ok(()),
+            }
+        }
+        (Type::Ref { name }, _) => anyhow::bail!(
+            "the rest of the path is in the schema `{name}`, which is shared; use \
+             `schemas.{name}.nullable` instead"
+        ),
+        (_, FieldSegment::Items) => anyhow::bail!("expected an array, but the type is not one"),
+        (_, FieldSegment::Field(name)) => {
+            anyhow::bail!("expected an object with the field `{name}`, but the type is not one")
+        }
+    }
+}
+
+/// Sorts the schemas so that every schema comes after the schemas it
+/// references, as the zod schemas for them are constants.  Otherwise,
+/// the order they were first referenced in is kept.
+///
+/// Schemas that reference each other in a cycle can't all come after
+/// each other; every schema on the cycle is marked as recursive, and
+/// its zod schema is made lazy instead.
+fn sort_schemas(mut schemas: IndexMap<String, Schema>) -> IndexMap<String, Schema> {
+    fn visit<'a>(
+        name: &'a str,
+        schemas: &'a IndexMap<String, Schema>,
+        visiting: &mut IndexSet<&'a str>,
+        order: &mut IndexSet<&'a str>,
+        recursive: &mut IndexSet<&'a str>,
+    ) {
+        if order.contains(name) {
+            return;
+        }
+        if !visiting.insert(name) {
+            recursive.extend(visiting.iter().skip_while(|n| **n != name));
+            return;
+        }
+        let mut refs = IndexSet::new();
+        schemas[name].ty.refs(&mut refs);
+        for dep in refs {
+            visit(dep, schemas, visiting, order, recursive);
+        }
+        visiting.shift_remove(name);
+        order.insert(name);
+    }
+
+    let mut order = IndexSet::new();
+    let mut recursive = IndexSet::new();
+    for name in schemas.keys() {
+        visit(
+            name,
+            &schemas,
+            &mut IndexSet::new(),
+            &mut order,
+            &mut recursive,
+        );
+    }
+    let order = order.into_iter().map(str::to_string).collect::<Vec<_>>();
+    let recursive = recursive
+        .into_iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    for name in recursive {
+        schemas[&name].recursive = true;
+    }
+    order
+        .into_iter()
+        .map(|name| {
+            let schema = schemas.shift_remove(&name).unwrap();
+            (name, schema)
+        })
+        .collect()
+}
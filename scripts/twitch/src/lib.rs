@@ -0,0 +1,80 @@
+//! Generates bindings to the twitch API based off of the OpenAPI
+//! spec.
+//!
+//! Because we have a... unique way of managing subscriptions and API
+//! keys, we don't want to force the user to manage the keys
+//! themselves. Instead, we'll provide a facade that will handle the
+//! keys for them.
+//!
+//! However, the surface area of the API is quite large, so we'll need
+//! to generate the bindings for the API. This is where this project
+//! comes in.
+//!
+//! Twitch does not generally publish their OpenAPI spec, so we'll use
+//! a project that generates the OpenAPI spec based on the Twitch API.
+//! The project is located [here].
+//!
+//! The generator is a pipeline, and every stage of it is public, so
+//! that it can be embedded (say, in a `build.rs`), or given a
+//! different back end:
+//!
+//! 1. [`file::load`] loads the spec, applying our patches and
+//!    overlays to it.
+//! 2. [`extract::Extract::new`] decides which namespaces, methods and
+//!    parameters the spec describes; [`filter::Filters`] trims that
+//!    down.
+//! 3. [`ir::Api::new`] turns that into the owned, normalized
+//!    representation that the client is generated from.
+//! 4. [`render::render`] (or [`render::render_files`]) generates the
+//!    client from it.
+//!
+//! [`build`] does steps 2 and 3 in one go, the same way the
+//! `twitch` binary does.
+//!
+//! [here]: https://github.com/DmitryScaletta/twitch-api-swagger
+
+pub mod check;
+pub mod config;
+pub mod diff;
+pub mod extract;
+pub mod file;
+pub mod filter;
+pub mod ir;
+pub mod lint;
+pub mod manifest;
+pub mod overlay;
+pub mod render;
+pub mod scopes;
+
+pub use openapiv3;
+
+/// Builds the API that the client is generated from.
+///
+/// The filters in the configuration are always applied; `filters`
+/// are applied on top of them, in order.  Every operation that the
+/// configuration has an entry for must be in the spec; see
+/// [`extract::Extract::check_overrides`].
+pub fn build(
+    api: &openapiv3::OpenAPI,
+    config: &config::Config,
+    filters: &[&filter::Filters],
+) -> Result<ir::Api, anyhow::Error> {
+    extract::Extract::check_overrides(api, config)?;
+    build_unchecked(api, config, filters)
+}
+
+/// Builds the API as [`build`] does, but allows the configuration to
+/// have entries for operations that the spec doesn't have; say, when
+/// the spec is an older one.
+pub fn build_unchecked(
+    api: &openapiv3::OpenAPI,
+    config: &config::Config,
+    filters: &[&filter::Filters],
+) -> Result<ir::Api, anyhow::Error> {
+    let mut extract = extract::Extract::new(api, config)?;
+    config.filters.apply(&mut extract)?;
+    for filters in filters {
+        filters.apply(&mut extract)?;
+    }
+    ir::Api::new(&extract, api, config)
+}
@@ -0,0 +1,128 @@
+//! Checks the extracted API for things the generator can technically
+//! handle, but that would make for a poor (or broken) client.
+
+use crate::extract::{Classification, Extract, ParamKind};
+use std::collections::HashSet;
+
+/// A single problem found in the extracted API.
+#[derive(Debug)]
+pub struct Finding {
+    /// Where the problem is, e.g. `moderation.banUser`.
+    pub location: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+pub fn lint(extract: &Extract<'_>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut push = |location: String, message: &str| {
+        findings.push(Finding {
+            location,
+            message: message.to_string(),
+        });
+    };
+
+    for (ns_name, namespace) in &extract.namespaces {
+        if namespace.methods.is_empty() {
+            push(
+                ns_name.clone(),
+                "namespace has no methods, and will generate an empty class",
+            );
+        }
+
+        let mut seen = HashSet::new();
+        for method in &namespace.methods {
+            let location = format!("{ns_name}.{}", method.name);
+            if !seen.insert(method.name.as_str()) {
+                push(
+                    location.clone(),
+                    "method name is used more than once in the namespace",
+                );
+            }
+            if method.description.is_none_or(str::is_empty) {
+                push(location.clone(), "method has no description");
+            }
+
+            for param in method.parameters.values() {
+                if param.description.is_none_or(str::is_empty) {
+                    push(
+                        format!("{location}({})", param.name),
+                        "parameter has no description",
+                    );
+                }
+                if param.kind == ParamKind::Path
+                    && !method.path.contains(&format!("{{{}}}", param.name))
+                {
+                    push(
+                        format!("{location}({})", param.name),
+                        "path parameter does not appear in the path",
+                    );
+                }
+            }
+
+            for placeholder in method.path.split('{').skip(1) {
+                let Some((name, _)) = placeholder.split_once('}') else {
+                    continue;
+                };
+                if !method
+                    .parameters
+                    .values()
+                    .any(|param| param.kind == ParamKind::Path && param.name == name)
+                {
+                    push(
+                        location.clone(),
+                        &format!("the path has a `{{{name}}}`, but no path parameter for it"),
+                    );
+                }
+            }
+        }
+    }
+
+    findings
+}
+
+/// How a single parameter was classified, and why.
+#[derive(Debug)]
+pub struct Explanation {
+    /// The parameter, e.g. `moderation.banUser(moderator_id)`.
+    pub location: String,
+    pub kind: ParamKind,
+    pub reason: String,
+}
+
+impl std::fmt::Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} ({})", self.location, self.kind, self.reason)
+    }
+}
+
+/// Explains how every parameter was classified; that is, which of
+/// `token-params.rules` (or which override) decided its kind.
+pub fn explain(extract: &Extract<'_>) -> Vec<Explanation> {
+    let mut explanations = Vec::new();
+    for (ns_name, namespace) in &extract.namespaces {
+        for method in &namespace.methods {
+            for param in method.parameters.values() {
+                let reason = match param.classification {
+                    Classification::Rule(rule) => format!("by the rule `{rule}`"),
+                    Classification::Override => {
+                        format!("by `operations.{}.token-params`", method.operation_id)
+                    }
+                    Classification::NoRule => "no rule matched".to_string(),
+                    Classification::Fixed => "never a user ID".to_string(),
+                };
+                explanations.push(Explanation {
+                    location: format!("{ns_name}.{}({})", method.name, param.name),
+                    kind: param.kind,
+                    reason,
+                });
+            }
+        }
+    }
+    explanations
+}
@@ -1,37 +1,422 @@
-//! Generates bindings to the twitch API based off of the OpenAPI
-//! spec.
-//!
-//! Because we have a... unique way of managing subscriptions and API
-//! keys, we don't want to force the user to manage the keys
-//! themselves. Instead, we'll provide a facade that will handle the
-//! keys for them.
-//!
-//! However, the surface area of the API is quite large, so we'll need
-//! to generate the bindings for the API. This is where this project
-//! comes in.
-//!
-//! Twitch does not generally publish their OpenAPI spec, so we'll use
-//! a project that generates the OpenAPI spec based on the Twitch API.
-//! The project is located [here].
-//!
-//! [here]: https://github.com/DmitryScaletta/twitch-api-swagger
+//! The command line interface to the generator.  Everything it does
+//! is done through the library; see the crate documentation there.
 
+use anyhow::Context as _;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
+use std::process::ExitCode;
+use twitch::{check, config, diff, extract, file, filter, ir, lint, render};
 
-mod extract;
-mod file;
-mod render;
+/// The exit code for when everything went fine.
+const EXIT_OK: u8 = 0;
+/// The exit code for when the command ran, but found problems (e.g.
+/// lints).
+const EXIT_FINDINGS: u8 = 1;
+/// The exit code for when the command itself failed.  This matches
+/// what clap uses for usage errors.
+const EXIT_ERROR: u8 = 2;
 
-fn main() -> Result<(), anyhow::Error> {
-    let api = file::load(std::env::args_os().nth(1))?;
-    let mut extract = self::extract::Extract::new(&api)?;
-    let out = std::env::args_os()
-        .nth(2)
-        .map(PathBuf::from)
-        .unwrap_or_else(|| PathBuf::from("./clients/twitch/api"));
-    extract.namespaces.remove("eventSub");
+/// Generates the Twitch API client from the community OpenAPI spec.
+///
+/// Settings are read from `twitch-gen.toml`, found in the working
+/// directory or any of its ancestors; flags given on the command line
+/// take precedence over it.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// The configuration file to use, instead of searching for one.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generates the client into the output directory of every
+    /// target.
+    ///
+    /// Files that a previous generation created, but that are no
+    /// longer generated, are removed.
+    Generate {
+        #[command(flatten)]
+        args: GenerateArgs,
+        /// Reports stale generated files, instead of removing them.
+        #[arg(long)]
+        keep_stale: bool,
+    },
+    /// Writes the API that the client is generated from to stdout,
+    /// as JSON.
+    DumpIr {
+        #[command(flatten)]
+        spec: SpecArgs,
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Applies the filters of the given target, too.
+        #[arg(long, short)]
+        target: Option<String>,
+    },
+    /// Compares two versions of the spec, and reports how the
+    /// generated client would change between them.
+    ///
+    /// Every change is classified as breaking or not, and the version
+    /// bump they call for is suggested.  Exits with 1 if there are
+    /// breaking changes.
+    Diff {
+        /// The old version of the spec, in JSON or YAML.
+        old: PathBuf,
+        /// The new version of the spec, in JSON or YAML.
+        new: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Human)]
+        format: Format,
+        /// Compares the specs as they are, without applying our
+        /// patches and overlays to them.
+        #[arg(long)]
+        no_patches: bool,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Checks that the output directory matches what would be
+    /// generated, without writing anything.  Prints a diff for every
+    /// stale, missing or extraneous file.
+    Check(GenerateArgs),
+    /// Checks the spec for things the generator cannot handle well,
+    /// without writing anything.
+    Lint {
+        #[command(flatten)]
+        spec: SpecArgs,
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Also prints the kind of every parameter, and the rule (or
+        /// override) in `token-params` that decided it.
+        #[arg(long)]
+        explain: bool,
+    },
+    /// Refreshes the cached spec from upstream, and records it in the
+    /// lockfile.
+    UpdateSpec {
+        /// The URL to download the spec from.
+        #[arg(long)]
+        source: Option<String>,
+        /// Where to write the updated spec to.
+        #[arg(long)]
+        spec: Option<PathBuf>,
+        /// The directory to cache the spec in.
+        #[arg(long)]
+        cache: Option<PathBuf>,
+    },
+}
+
+/// How to print a report.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Human,
+    Json,
+}
+
+#[derive(Debug, Args)]
+struct GenerateArgs {
+    #[command(flatten)]
+    spec: SpecArgs,
+    #[command(flatten)]
+    filter: FilterArgs,
+    /// Only generates the target with the given name.
+    #[arg(long, short)]
+    target: Option<String>,
+    /// The directory to write the generated client to, instead of the
+    /// one configured for the target.
+    #[arg(long, short)]
+    out: Option<PathBuf>,
+}
+
+impl GenerateArgs {
+    /// Determines the targets to generate.
+    fn targets(&self, config: &config::Config) -> Result<Vec<config::Target>, anyhow::Error> {
+        let mut targets = match self.target.as_deref() {
+            Some(name) => vec![config.target(name)?.clone()],
+            None => config.targets.clone(),
+        };
+        if let Some(out) = self.out.as_ref() {
+            let [target] = &mut targets[..] else {
+                anyhow::bail!(
+                    "`--out` can only be used with a single target; pick one with `--target`"
+                );
+            };
+            target.out.clone_from(out);
+        }
+        Ok(targets)
+    }
+
+    /// Loads the spec, and builds the API for every target.
+    fn for_each_target<F>(&self, config: &config::Config, mut f: F) -> Result<(), anyhow::Error>
+    where
+        F: FnMut(&config::Target, &ir::Api) -> Result<(), anyhow::Error>,
+    {
+        let targets = self.targets(config)?;
+        let api = self.spec.load(config)?;
+        for target in &targets {
+            let ir = twitch::build(&api, config, &[&target.filters, &self.filter.filters()])?;
+            f(target, &ir)
+                .with_context(|| format!("when generating the target `{}`", target.name))?;
+        }
+        Ok(())
+    }
+}
+
+/// Where to load the spec from.
+#[derive(Debug, Args)]
+struct SpecArgs {
+    /// The path to the OpenAPI spec, in JSON or YAML; `-` reads it
+    /// from stdin.  It is downloaded if it does not exist.
+    #[arg(long, short)]
+    spec: Option<PathBuf>,
+    /// Refuses to download the spec, and fails if it does not match
+    /// the lockfile.
+    #[arg(long, alias = "frozen")]
+    offline: bool,
+}
+
+impl SpecArgs {
+    fn load(&self, config: &config::Config) -> Result<openapiv3::OpenAPI, anyhow::Error> {
+        let (api, lock) = file::load_locked(&file::LoadOptions {
+            path: Some(
+                self.spec
+                    .clone()
+                    .unwrap_or_else(|| config.spec.path.clone()),
+            ),
+            source: Some(config.spec.source.clone()),
+            patches: Some(config.spec.patches.clone()),
+            // unlike patches, we may not have any overlays at all.
+            overlays: Some(config.spec.overlays.clone()).filter(|dir| dir.is_dir()),
+            lock: Some(config.spec.lock.clone()),
+            frozen: self.offline,
+        })?;
+        // on stderr, so as not to get mixed up with `dump-ir`.
+        match lock {
+            file::LockUpdate::Unchanged => {}
+            file::LockUpdate::Created { sha256 } => eprintln!(
+                "created the lockfile {}, for the spec with the hash {sha256}",
+                config.spec.lock.display()
+            ),
+            file::LockUpdate::Changed { previous, sha256 } => eprintln!(
+                "warning: the spec has the hash {sha256}, but the lockfile {} expected \
+                 {previous}; the lockfile was updated (pass --offline to fail instead)",
+                config.spec.lock.display()
+            ),
+            file::LockUpdate::SourceChanged { previous, source } => eprintln!(
+                "warning: the spec was loaded from {source}, but the lockfile {} recorded \
+                 {previous}; the lockfile was updated (pass --offline to fail instead)",
+                config.spec.lock.display()
+            ),
+        }
+        Ok(api)
+    }
+}
+
+/// Which parts of the API to generate, on top of the filters in the
+/// configuration.
+#[derive(Debug, Args)]
+struct FilterArgs {
+    /// Only generates the given namespace.  May be given multiple
+    /// times.
+    #[arg(long, value_name = "NAMESPACE")]
+    include_namespace: Vec<String>,
+    /// Skips the given namespace.  May be given multiple times.
+    #[arg(long, value_name = "NAMESPACE")]
+    exclude_namespace: Vec<String>,
+    /// Only generates the operation with the given operationId.  May
+    /// be given multiple times.
+    #[arg(long, value_name = "OPERATION")]
+    include_operation: Vec<String>,
+    /// Skips the operation with the given operationId.  May be given
+    /// multiple times.
+    #[arg(long, value_name = "OPERATION")]
+    exclude_operation: Vec<String>,
+    /// Only generates operations whose path matches the given glob.
+    /// May be given multiple times.
+    #[arg(long, value_name = "GLOB")]
+    include_path: Vec<String>,
+    /// Skips operations whose path matches the given glob.  May be
+    /// given multiple times.
+    #[arg(long, value_name = "GLOB")]
+    exclude_path: Vec<String>,
+}
+
+impl FilterArgs {
+    fn filters(&self) -> filter::Filters {
+        filter::Filters {
+            include: filter::FilterRules {
+                namespaces: self.include_namespace.clone(),
+                operations: self.include_operation.clone(),
+                paths: self.include_path.clone(),
+            },
+            exclude: filter::FilterRules {
+                namespaces: self.exclude_namespace.clone(),
+                operations: self.exclude_operation.clone(),
+                paths: self.exclude_path.clone(),
+            },
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(code) => ExitCode::from(code),
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<u8, anyhow::Error> {
+    let config = config::Config::load(cli.config.as_deref())?;
+
+    match cli.command {
+        Command::Generate { args, keep_stale } => {
+            args.for_each_target(&config, |target, api| {
+                let stale = render::render(api, &config.render, &target.out, !keep_stale)?;
+                for file in &stale {
+                    let path = target.out.join(&file.name);
+                    if file.removed {
+                        eprintln!("removed stale file: {}", path.display());
+                    } else {
+                        eprintln!("stale file left in place: {}", path.display());
+                    }
+                }
+                Ok(())
+            })?;
+            Ok(EXIT_OK)
+        }
+        Command::Check(args) => {
+            let mut clean = true;
+            args.for_each_target(&config, |target, api| {
+                let files = render::render_files(api, &config.render)?;
+                let drift = check::check(&files, &target.out)?;
+                for file in &drift {
+                    print!("{}", file.diff);
+                    eprintln!("{}: {}", file.kind, target.out.join(&file.name).display());
+                }
+                clean &= drift.is_empty();
+                Ok(())
+            })?;
+            Ok(if clean { EXIT_OK } else { EXIT_FINDINGS })
+        }
+        Command::Lint {
+            spec,
+            filter,
+            explain,
+        } => {
+            let api = spec.load(&config)?;
+            extract::Extract::check_overrides(&api, &config)?;
+            let mut extract = extract::Extract::new(&api, &config)?;
+            config.filters.apply(&mut extract)?;
+            filter.filters().apply(&mut extract)?;
+            if explain {
+                for explanation in lint::explain(&extract) {
+                    println!("{explanation}");
+                }
+            }
+            let findings = lint::lint(&extract);
+            for finding in &findings {
+                println!("{finding}");
+            }
+            Ok(if findings.is_empty() {
+                EXIT_OK
+            } else {
+                EXIT_FINDINGS
+            })
+        }
+        Command::DumpIr {
+            spec,
+            filter,
+            target,
+        } => {
+            let api = spec.load(&config)?;
+            let mut filters = Vec::new();
+            if let Some(target) = target.as_deref() {
+                filters.push(config.target(target)?.filters.clone());
+            }
+            filters.push(filter.filters());
+            let ir = twitch::build(&api, &config, &filters.iter().collect::<Vec<_>>())?;
+            println!("{}", serde_json::to_string_pretty(&ir)?);
+            Ok(EXIT_OK)
+        }
+        Command::Diff {
+            old,
+            new,
+            format,
+            no_patches,
+            filter,
+        } => {
+            let load = |path: PathBuf| {
+                file::load(&file::LoadOptions {
+                    path: Some(path),
+                    patches: Some(config.spec.patches.clone()).filter(|_| !no_patches),
+                    overlays: Some(config.spec.overlays.clone())
+                        .filter(|dir| !no_patches && dir.is_dir()),
+                    // never download anything, nor touch the lockfile.
+                    frozen: true,
+                    ..Default::default()
+                })
+            };
+            let old_api = load(old).context("when loading the old spec")?;
+            let new_api = load(new).context("when loading the new spec")?;
+            let filters = filter.filters();
+            // the configuration is written for the spec as it is now,
+            // so either spec may lack operations it has entries for.
+            let old = twitch::build_unchecked(&old_api, &config, &[&filters])
+                .context("in the old spec")?;
+            let new = twitch::build_unchecked(&new_api, &config, &[&filters])
+                .context("in the new spec")?;
 
-    self::render::render(&extract, &api, &out)?;
+            let changes = diff::diff(&old, &new);
+            let bump = diff::bump(&changes);
+            match format {
+                Format::Human => {
+                    for (breaking, heading) in
+                        [(true, "breaking changes"), (false, "non-breaking changes")]
+                    {
+                        let mut changes =
+                            changes.iter().filter(|c| c.breaking == breaking).peekable();
+                        if changes.peek().is_some() {
+                            println!("{heading}:");
+                            for change in changes {
+                                println!("  {change}");
+                            }
+                        }
+                    }
+                    println!("suggested version bump: {bump}");
+                }
+                Format::Json => {
+                    let report = serde_json::json!({ "bump": bump, "changes": changes });
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                }
+            }
 
-    Ok(())
+            Ok(if bump == diff::Bump::Major {
+                EXIT_FINDINGS
+            } else {
+                EXIT_OK
+            })
+        }
+        Command::UpdateSpec {
+            source,
+            spec,
+            cache,
+        } => {
+            let update = file::update(&file::UpdateOptions {
+                source: source.unwrap_or(config.spec.source),
+                cache: cache.unwrap_or(config.spec.cache),
+                path: spec.unwrap_or(config.spec.path),
+                lock: Some(config.spec.lock),
+            })?;
+            match update {
+                file::SpecUpdate::Changed => println!("the upstream spec has changed"),
+                file::SpecUpdate::Unchanged => println!("the upstream spec is unchanged"),
+            }
+            Ok(EXIT_OK)
+        }
+    }
 }
@@ -0,0 +1,102 @@
+//! Tracks which files in the output directory were generated by us.
+//!
+//! Every generation writes a manifest, listing the files it
+//! generated, into the output directory.  When a namespace disappears
+//! upstream, the next generation can then tell that the file it used
+//! to generate for it is stale, without ever mistaking a hand-written
+//! file for a generated one.
+
+use anyhow::Context as _;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// The name of the manifest file, in the output directory.
+pub const MANIFEST_FILE: &str = ".twitch-gen-manifest.json";
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+    /// The generated files, relative to the output directory.  This
+    /// does not include the manifest itself.
+    pub files: BTreeSet<String>,
+}
+
+impl Manifest {
+    /// Serializes the manifest, as it is written to disk.
+    pub fn to_vec(&self) -> Result<Vec<u8>, anyhow::Error> {
+        let mut out = serde_json::to_vec_pretty(self)?;
+        out.push(b'\n');
+        Ok(out)
+    }
+
+    /// Reads the manifest from the output directory, if there is one.
+    pub fn read(dir: &Path) -> Result<Option<Self>, anyhow::Error> {
+        let path = dir.join(MANIFEST_FILE);
+        match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map(Some)
+                .with_context(|| format!("when parsing the manifest {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => {
+                Err(e).with_context(|| format!("when reading the manifest {}", path.display()))
+            }
+        }
+    }
+}
+
+/// Determines which files in the output directory we own.
+///
+/// This is what the manifest says, if there is one.  Otherwise (say,
+/// for an output directory from before we wrote manifests), we fall
+/// back to looking for the generated file notice in every file.
+pub fn owned_files(dir: &Path) -> Result<BTreeSet<String>, anyhow::Error> {
+    if let Some(manifest) = Manifest::read(dir)? {
+        return Ok(manifest.files);
+    }
+
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeSet::new()),
+        Err(e) => return Err(e).with_context(|| format!("when reading {}", dir.display())),
+    };
+
+    let mut owned = BTreeSet::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("when reading {}", dir.display()))?;
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let contents = std::fs::read(entry.path())
+            .with_context(|| format!("when reading {}", entry.path().display()))?;
+        if crate::render::is_generated(&contents) {
+            owned.insert(name);
+        }
+    }
+    Ok(owned)
+}
+
+/// Removes the given stale files from the output directory.
+///
+/// As a last line of defense, a file is only removed if it still
+/// carries the generated file notice; the names of the files that
+/// were left alone because of that are returned.
+pub fn remove_stale(dir: &Path, stale: &[String]) -> Result<Vec<String>, anyhow::Error> {
+    let mut kept = Vec::new();
+    for name in stale {
+        let path = dir.join(name);
+        let contents = match std::fs::read(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("when reading {}", path.display())),
+        };
+        if crate::render::is_generated(&contents) {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("when removing {}", path.display()))?;
+        } else {
+            kept.push(name.clone());
+        }
+    }
+    Ok(kept)
+}
@@ -0,0 +1,191 @@
+//! Overlays: extra OpenAPI fragments merged into the upstream spec.
+//!
+//! The community spec does not document every endpoint we use; some
+//! are undocumented, and some are private.  Rather than forking the
+//! spec, we keep those endpoints in small OpenAPI documents of their
+//! own, and merge them into the spec before extraction.  An overlay
+//! may only contain `paths`, `components` and `tags` (plus `openapi`
+//! and `info`, which are ignored, so that an overlay can be a valid
+//! OpenAPI document on its own).
+//!
+//! Overlays only ever _add_ to the spec.  If an overlay defines an
+//! operation, component or tag that the spec (or an earlier overlay)
+//! already defines differently, that is reported as a conflict,
+//! rather than silently overwriting it; to change what upstream
+//! defines, use a patch instead.
+
+use anyhow::Context as _;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// The directory containing the overlays we merge into the upstream
+/// spec, relative to the root of the repository.
+pub const DEFAULT_OVERLAY_DIR: &str = "./scripts/twitch/overlays";
+
+/// The top-level keys an overlay may have.
+const ALLOWED_KEYS: &[&str] = &["openapi", "info", "paths", "components", "tags"];
+
+/// A single overlay file.
+#[derive(Debug)]
+pub struct Overlay {
+    path: PathBuf,
+    document: Map<String, Value>,
+}
+
+impl Overlay {
+    /// Merges this overlay into the given spec.
+    ///
+    /// All conflicts are collected, and reported together; if there
+    /// are any, the spec is left partially merged, and should be
+    /// discarded.
+    pub fn apply(&self, api: &mut Value) -> Result<(), anyhow::Error> {
+        let conflicts = self
+            .merge(api)
+            .with_context(|| format!("when applying the overlay {}", self.path.display()))?;
+
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            anyhow::bail!(
+                "the overlay {} conflicts with what is already defined:\n  {}",
+                self.path.display(),
+                conflicts.join("\n  ")
+            )
+        }
+    }
+
+    fn merge(&self, api: &mut Value) -> Result<Vec<String>, anyhow::Error> {
+        let Some(api) = api.as_object_mut() else {
+            anyhow::bail!("the openapi file is not an object");
+        };
+        let mut conflicts = Vec::new();
+
+        if let Some(paths) = self.document.get("paths") {
+            let target = section(api, "paths")?;
+            for (path, item) in as_object(paths, "paths")? {
+                let Some(existing) = target.get_mut(path) else {
+                    target.insert(path.clone(), item.clone());
+                    continue;
+                };
+                let Some(existing) = existing.as_object_mut() else {
+                    anyhow::bail!("the path `{path}` in the openapi file is not an object");
+                };
+                for (key, value) in as_object(item, &format!("paths.{path}"))? {
+                    merge_entry(existing, key, value, &mut conflicts, || {
+                        format!("paths.{path}.{key}")
+                    });
+                }
+            }
+        }
+
+        if let Some(components) = self.document.get("components") {
+            let target = section(api, "components")?;
+            for (kind, entries) in as_object(components, "components")? {
+                let target = section(target, kind)?;
+                for (name, value) in as_object(entries, &format!("components.{kind}"))? {
+                    merge_entry(target, name, value, &mut conflicts, || {
+                        format!("components.{kind}.{name}")
+                    });
+                }
+            }
+        }
+
+        if let Some(tags) = self.document.get("tags") {
+            let Some(tags) = tags.as_array() else {
+                anyhow::bail!("`tags` in the overlay is not an array");
+            };
+            let target = api
+                .entry("tags")
+                .or_insert_with(|| Value::Array(Vec::new()))
+                .as_array_mut()
+                .context("`tags` in the openapi file is not an array")?;
+            for tag in tags {
+                let name = tag.get("name").and_then(Value::as_str);
+                match target
+                    .iter()
+                    .find(|existing| existing.get("name").and_then(Value::as_str) == name)
+                {
+                    None => target.push(tag.clone()),
+                    Some(existing) if existing == tag => {}
+                    Some(_) => conflicts.push(format!("tags.{}", name.unwrap_or("<unnamed>"))),
+                }
+            }
+        }
+
+        Ok(conflicts)
+    }
+}
+
+/// Loads all the overlays in the given directory, in file name order.
+pub fn load_overlays(dir: &Path) -> Result<Vec<Overlay>, anyhow::Error> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("when reading the overlay directory {}", dir.display()))?;
+    let mut paths = entries
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("when reading the overlay directory {}", dir.display()))?;
+    paths.retain(|path| {
+        path.extension()
+            .is_some_and(|ext| ext == "json" || ext == "yaml" || ext == "yml")
+    });
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let contents = std::fs::read(&path)
+                .with_context(|| format!("when reading the overlay {}", path.display()))?;
+            let document = crate::file::parse_document(&contents, Some(&path))
+                .with_context(|| format!("when parsing the overlay {}", path.display()))?;
+            let Value::Object(document) = document else {
+                anyhow::bail!("the overlay {} is not an object", path.display());
+            };
+            if let Some(key) = document
+                .keys()
+                .find(|key| !ALLOWED_KEYS.contains(&key.as_str()))
+            {
+                anyhow::bail!(
+                    "the overlay {} has the key `{key}`, but overlays may only contain {}",
+                    path.display(),
+                    ALLOWED_KEYS.join(", ")
+                );
+            }
+            Ok(Overlay { path, document })
+        })
+        .collect()
+}
+
+/// Inserts `value` at `key`, unless something different is already
+/// there.
+fn merge_entry<F: FnOnce() -> String>(
+    target: &mut Map<String, Value>,
+    key: &str,
+    value: &Value,
+    conflicts: &mut Vec<String>,
+    describe: F,
+) {
+    match target.get(key) {
+        None => {
+            target.insert(key.to_string(), value.clone());
+        }
+        Some(existing) if existing == value => {}
+        Some(_) => conflicts.push(describe()),
+    }
+}
+
+/// Retrieves the object at `key`, creating it if it does not exist.
+fn section<'m>(
+    map: &'m mut Map<String, Value>,
+    key: &str,
+) -> Result<&'m mut Map<String, Value>, anyhow::Error> {
+    map.entry(key)
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .with_context(|| format!("`{key}` in the openapi file is not an object"))
+}
+
+fn as_object<'v>(value: &'v Value, what: &str) -> Result<&'v Map<String, Value>, anyhow::Error> {
+    value
+        .as_object()
+        .with_context(|| format!("`{what}` in the overlay is not an object"))
+}
@@ -0,0 +1,51 @@
+use crate::ir::Method;
+use heck::ToUpperCamelCase as _;
+
+impl<W> super::Render<'_, W>
+where
+    W: std::io::Write,
+{
+    /// Renders the error class for the method, if the spec documents
+    /// any errors for it.  It extends `TwitchError`, with the
+    /// documented statuses as its type parameter, and the
+    /// descriptions of them; it is only thrown for those statuses
+    /// (see `createError`, in the errors module).
+    pub(super) fn render_errors(&mut self, method: &Method) -> Result<(), anyhow::Error> {
+        let Some(name) = error_name(method) else {
+            return Ok(());
+        };
+        let statuses = method
+            .errors
+            .iter()
+            .map(|error| error.status.to_string())
+            .collect::<Vec<_>>()
+            .join(" | ");
+
+        writeln!(
+            self.writer,
+            "/**\n * The error `{method}` throws when the API responds with one of the errors \
+             it\n * documents.\n */\nexport class {name} extends TwitchError<{statuses}> {{\n  \
+             static readonly descriptions: Readonly<Record<number, string>> = {{",
+            method = method.name,
+        )?;
+        for error in &method.errors {
+            writeln!(
+                self.writer,
+                "    {status}: \"{description}\",",
+                status = error.status,
+                description = error.description.trim().escape_default()
+            )?;
+        }
+        writeln!(
+            self.writer,
+            "  }};\n\n  constructor(status: number, body: unknown) {{\n    super(status, body, \
+             {name}.descriptions[status]);\n  }}\n}}\n"
+        )?;
+        Ok(())
+    }
+}
+
+/// The name of the error class for the method, if it has one.
+pub(super) fn error_name(method: &Method) -> Option<String> {
+    (!method.errors.is_empty()).then(|| format!("{}Error", method.name).to_upper_camel_case())
+}
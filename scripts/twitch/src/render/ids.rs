@@ -0,0 +1,21 @@
+use crate::ir::Api;
+
+impl<W> super::Render<'_, W>
+where
+    W: std::io::Write,
+{
+    /// Renders the kinds of IDs, each as a branded zod schema and a
+    /// type of the same name.  A branded string is still a string,
+    /// but a plain string (or an ID of another kind) isn't one.
+    pub(super) fn render_ids(&mut self, api: &Api) -> Result<(), anyhow::Error> {
+        for (name, description) in &api.ids {
+            self.write_schema_comment(description.as_deref())?;
+            writeln!(
+                self.writer,
+                "export const {name} = z.string().brand(\"{name}\");\nexport type {name} = \
+                 z.infer<typeof {name}>;\n"
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
-use crate::extract::{ExtractNamespaceMethod, ExtractNamespaceMethodParam, ParamKind};
+use crate::extract::ParamKind;
+use crate::ir::{Method, Param, Type};
 use heck::ToUpperCamelCase;
 use std::borrow::Cow;
 
@@ -6,28 +7,34 @@ impl<W> super::Render<'_, W>
 where
     W: std::io::Write,
 {
-    pub(super) fn render_method(
-        &mut self,
-        method: &ExtractNamespaceMethod<'_>,
-    ) -> Result<(), anyhow::Error> {
+    pub(super) fn render_method(&mut self, method: &Method) -> Result<(), anyhow::Error> {
         let function_param_ty = format!("{}Request", method.name).to_upper_camel_case();
         let function_ret = if method.download {
             Cow::Borrowed("Response")
-        } else if method.ret.is_some() {
-            format!("{}Response", method.name)
-                .to_upper_camel_case()
-                .into()
+        } else if method.response.is_some() {
+            let name = format!("{}Response", method.name).to_upper_camel_case();
+            if method.may_be_empty {
+                format!("{name} | undefined").into()
+            } else {
+                name.into()
+            }
         } else {
             "void".into()
         };
         let function_name = &method.name;
-        let comment = super::wrap_comment(method.description.unwrap_or(""), 74);
+        let comment = super::wrap_comment(
+            method.description.as_deref().unwrap_or(""),
+            self.config.comment_width,
+        );
         writeln!(self.writer, "  /**")?;
         for line in comment.trim_end().split('\n') {
             writeln!(self.writer, "   * {}", line)?;
         }
         write!(self.writer, "   */\n  async {function_name}(")?;
-        if method.has_active_params() {
+        if method.has_only_optional_params() && method.has_params() {
+            // so that callers can still leave out the request entirely.
+            write!(self.writer, "options: {function_param_ty} = {{}}")?;
+        } else if method.has_params() {
             write!(self.writer, "options: {function_param_ty}")?;
         }
         writeln!(self.writer, "): Promise<{function_ret}> {{")?;
@@ -37,32 +44,49 @@ where
         Ok(())
     }
 
-    pub(super) fn render_method_body(
-        &mut self,
-        method: &ExtractNamespaceMethod<'_>,
-    ) -> Result<(), anyhow::Error> {
+    pub(super) fn render_method_body(&mut self, method: &Method) -> Result<(), anyhow::Error> {
         writeln!(
             self.writer,
             "    const snapshot = await this.#twitch.snapshot();"
         )?;
-        if let Some(required_scopes) = method.requires_scopes.as_ref() {
-            // teehee.
-            // technically we should use escape_default, instead of debug
-            // format, but.
-            // teehee.
+        if !method.requires_scopes.is_empty() {
+            // any one set of scopes will do; if none of them do, we
+            // report what is missing from each.
+            let sets = method
+                .requires_scopes
+                .iter()
+                .map(|set| {
+                    let scopes = set
+                        .iter()
+                        .map(|scope| format!("\"{}\"", scope.escape_default()))
+                        .collect::<Vec<_>>();
+                    format!("[{}]", scopes.join(", "))
+                })
+                .collect::<Vec<_>>();
             writeln!(
                 self.writer,
-                "    const requiredScopes = {required_scopes:?};\n    const missingScopes = \
-                 requiredScopes.filter((scope) => !snapshot.scopes.includes(scope));\n    if \
-                 (missingScopes.length > 0) {{\n      throw new Error(`missing required scopes: \
-                 ${{missingScopes.join(', ')}}`);\n    }}"
+                "    const requiredScopes = [{sets}];\n    const missingScopes = \
+                 requiredScopes.map((scopes) =>\n      scopes.filter((scope) => \
+                 !snapshot.scopes.includes(scope))\n    );\n    if (missingScopes.every((missing) \
+                 => missing.length > 0)) {{\n      throw new Error(\n        `missing required \
+                 scopes: ${{missingScopes.map((missing) => missing.join(\" and \")).join(\", or \
+                 \")}}`\n      );\n    }}",
+                sets = sets.join(", ")
+            )?;
+        }
+        if method.path_params().next().is_some() {
+            writeln!(
+                self.writer,
+                "    const url = new URL(`{}`, this.#twitch.base);",
+                PathTemplate(method)
+            )?;
+        } else {
+            writeln!(
+                self.writer,
+                "    const url = new URL(\"{}\", this.#twitch.base);",
+                method.path.escape_default()
             )?;
         }
-        writeln!(
-            self.writer,
-            "    const url = new URL(\"{}\", this.#twitch.base);",
-            method.path.escape_default()
-        )?;
         for param in method.query_params() {
             self.render_method_query(param)?;
         }
@@ -71,6 +95,13 @@ where
             "    const opts: RequestInit = {{ method: '{method}' }};\n",
             method = method.method.to_ascii_uppercase().escape_default()
         )?;
+        if method.header_params().next().is_some() {
+            writeln!(self.writer, "    const headers = new Headers();")?;
+            for param in method.header_params() {
+                self.render_method_header(param)?;
+            }
+            writeln!(self.writer, "    opts.headers = headers;\n")?;
+        }
         let has_body = method.has_body();
         if has_body {
             writeln!(
@@ -85,36 +116,41 @@ where
             writeln!(self.writer, "    opts.body = JSON.stringify(body);")?;
         }
 
+        let args = request_args(method);
         if method.download {
             writeln!(
                 self.writer,
-                "    return await this.#twitch.requestDownload(url, opts);"
+                "    return await this.#twitch.requestDownload({args});"
             )?;
-        } else if method.ret.is_some() {
-            let ztype = format!("{}Response", method.name).to_upper_camel_case();
+        } else if method.response.is_some() {
             writeln!(
                 self.writer,
-                "    return await this.#twitch.request(url, opts, {ztype});"
+                "    return await this.#twitch.request({args});"
             )?;
         } else {
-            writeln!(self.writer, "    await this.#twitch.request(url, opts);")?;
+            writeln!(self.writer, "    await this.#twitch.request({args});")?;
         }
         Ok(())
     }
 
-    pub(super) fn render_method_query(
-        &mut self,
-        param: &ExtractNamespaceMethodParam<'_>,
-    ) -> Result<(), anyhow::Error> {
+    pub(super) fn render_method_query(&mut self, param: &Param) -> Result<(), anyhow::Error> {
         match param.kind {
             ParamKind::Query | ParamKind::QueryUserToken => {
+                let guarded = param.optional || param.ty.is_nullable();
                 let indent = if param.optional {
                     writeln!(self.writer, "    if (options.{id}) {{", id = param.id,)?;
                     "  "
+                } else if guarded {
+                    writeln!(
+                        self.writer,
+                        "    if (options.{id} !== null) {{",
+                        id = param.id,
+                    )?;
+                    "  "
                 } else {
                     ""
                 };
-                if matches!(param.ty, openapiv3::Type::Array(_)) {
+                if matches!(param.ty.non_null(), Type::Array { .. }) {
                     writeln!(
                         self.writer,
                         "{indent}    for (const value of options.{id}) {{\n      \
@@ -130,7 +166,7 @@ where
                         name = param.name.escape_default()
                     )?;
                 }
-                if param.optional {
+                if guarded {
                     writeln!(self.writer, "    }}")?;
                 }
                 Ok(())
@@ -138,23 +174,51 @@ where
             ParamKind::QueryCurrentUserToken => {
                 writeln!(
                     self.writer,
-                    "    url.searchParams.append(\"{name}\", snapshot.userId);",
+                    "    url.searchParams.append(\"{name}\", options.{id} ?? snapshot.userId);",
+                    id = param.id,
                     name = param.name.escape_default()
                 )?;
                 Ok(())
             }
-            ParamKind::Body | ParamKind::BodyCurrentUserToken | ParamKind::BodyUserToken => {
+            ParamKind::Body
+            | ParamKind::BodyCurrentUserToken
+            | ParamKind::BodyUserToken
+            | ParamKind::Path
+            | ParamKind::Header => {
                 unreachable!(
-                    "unexpected query parameter kind; body parameters will not be called here"
+                    "unexpected query parameter kind; only query parameters are rendered here"
                 )
             }
         }
     }
 
-    pub(super) fn render_method_body_param(
-        &mut self,
-        param: &ExtractNamespaceMethodParam<'_>,
-    ) -> Result<(), anyhow::Error> {
+    pub(super) fn render_method_header(&mut self, param: &Param) -> Result<(), anyhow::Error> {
+        let nullable = param.ty.is_nullable();
+        let indent = if param.optional || nullable {
+            // a loose comparison skips `undefined` as well as `null`.
+            let check = if nullable { "!= null" } else { "!== undefined" };
+            writeln!(
+                self.writer,
+                "    if (options.{id} {check}) {{",
+                id = param.id
+            )?;
+            "  "
+        } else {
+            ""
+        };
+        writeln!(
+            self.writer,
+            "{indent}    headers.set(\"{name}\", options.{id}.toString());",
+            id = param.id,
+            name = param.name.escape_default()
+        )?;
+        if param.optional || nullable {
+            writeln!(self.writer, "    }}")?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn render_method_body_param(&mut self, param: &Param) -> Result<(), anyhow::Error> {
         struct BodyAccess<'n>(&'n str);
         impl std::fmt::Display for BodyAccess<'_> {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -180,16 +244,86 @@ where
             ParamKind::BodyCurrentUserToken => {
                 writeln!(
                     self.writer,
-                    "    {body} = snapshot.userId;",
+                    "    {body} = options.{id} ?? snapshot.userId;",
+                    id = param.id,
                     body = BodyAccess(&param.name)
                 )?;
                 Ok(())
             }
-            ParamKind::Query | ParamKind::QueryCurrentUserToken | ParamKind::QueryUserToken => {
+            ParamKind::Query
+            | ParamKind::QueryCurrentUserToken
+            | ParamKind::QueryUserToken
+            | ParamKind::Path
+            | ParamKind::Header => {
                 unreachable!(
-                    "unexpected query parameter kind; body parameters will not be called here"
+                    "unexpected body parameter kind; only body parameters are rendered here"
                 )
             }
         }
     }
 }
+
+/// The arguments to `Twitch.request` (or `Twitch.requestDownload`),
+/// leaving off the trailing ones that would be `undefined`.
+fn request_args(method: &Method) -> String {
+    let mut args = vec!["url".to_string(), "opts".to_string()];
+    if !method.download {
+        args.push(match method.response {
+            Some(_) => format!("{}Response", method.name).to_upper_camel_case(),
+            None => "undefined".to_string(),
+        });
+    }
+    args.push(if method.statuses.is_empty() {
+        "undefined".to_string()
+    } else {
+        let statuses = method
+            .statuses
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<_>>();
+        format!("[{}]", statuses.join(", "))
+    });
+    args.push(super::errors::error_name(method).unwrap_or_else(|| "undefined".to_string()));
+
+    while args.last().is_some_and(|arg| arg == "undefined") {
+        args.pop();
+    }
+    args.join(", ")
+}
+
+/// The path of a method, as the body of a template literal, with the
+/// path parameters substituted into it.
+struct PathTemplate<'m>(&'m Method);
+
+impl std::fmt::Display for PathTemplate<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut rest = self.0.path.as_str();
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+                break;
+            };
+            write_literal(f, &rest[..start])?;
+            let name = &rest[start + 1..end];
+            match self.0.path_params().find(|param| param.name == name) {
+                Some(param) => write!(
+                    f,
+                    "${{encodeURIComponent(options.{}.toString())}}",
+                    param.id
+                )?,
+                None => write_literal(f, &rest[start..=end])?,
+            }
+            rest = &rest[end + 1..];
+        }
+        write_literal(f, rest)
+    }
+}
+
+fn write_literal(f: &mut std::fmt::Formatter<'_>, text: &str) -> std::fmt::Result {
+    for c in text.chars() {
+        match c {
+            '`' | '$' => write!(f, "\\{c}")?,
+            c => write!(f, "{}", c.escape_default())?,
+        }
+    }
+    Ok(())
+}
@@ -1,19 +1,59 @@
+mod errors;
+mod ids;
 mod method;
 mod response;
+mod scopes;
 mod types;
 
-use crate::extract::{Extract, ExtractNamespace, ExtractNamespaceMethod};
+use crate::config::RenderConfig;
+use crate::ir::{Api, Method, Namespace, Schema};
 use anyhow::Context as _;
 use heck::ToUpperCamelCase as _;
-use std::collections::VecDeque;
+use indexmap::{IndexMap, IndexSet};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::io::Write as _;
 use std::path::Path;
+use types::Side;
 
+/// The rendered client, keyed by file name, relative to the output
+/// directory.
+pub type Files = BTreeMap<String, Vec<u8>>;
+
+/// Renders the client, and writes it to the output directory.
+///
+/// Files that we generated on a previous run, but no longer generate,
+/// are stale.  If `prune` is set, they are removed (as long as they
+/// still look generated; see [`crate::manifest::remove_stale`]);
+/// otherwise, they are left in place, and kept in the manifest, so
+/// that a later run can still remove them.  Either way, they are
+/// returned.
 pub fn render(
-    extract: &Extract<'_>,
-    api: &openapiv3::OpenAPI,
+    api: &Api,
+    config: &RenderConfig,
     dir: &Path,
-) -> Result<(), anyhow::Error> {
+    prune: bool,
+) -> Result<Vec<StaleFile>, anyhow::Error> {
+    let mut files = render_files(api, config)?;
+    let stale = crate::manifest::owned_files(dir)?
+        .into_iter()
+        .filter(|name| !files.contains_key(name))
+        .collect::<Vec<_>>();
+
+    let kept = if prune {
+        crate::manifest::remove_stale(dir, &stale)?
+    } else {
+        let mut manifest = crate::manifest::Manifest {
+            files: files.keys().cloned().collect(),
+        };
+        manifest.files.remove(crate::manifest::MANIFEST_FILE);
+        manifest.files.extend(stale.iter().cloned());
+        files.insert(
+            crate::manifest::MANIFEST_FILE.to_string(),
+            manifest.to_vec()?,
+        );
+        stale.clone()
+    };
+
     std::fs::create_dir_all(dir).with_context(|| {
         format!(
             "when attempting to create the output directory {}",
@@ -21,47 +61,191 @@ pub fn render(
         )
     })?;
 
-    for (name, namespace) in &extract.namespaces {
-        let path = dir.join(format!("{}.ts", name));
-        let mut file = std::fs::File::create(&path).with_context(|| {
+    for (name, contents) in &files {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).with_context(|| {
             format!(
-                "when attempting to create the output file {}",
+                "when attempting to write the output file {}",
                 path.display()
             )
         })?;
+    }
+
+    Ok(stale
+        .into_iter()
+        .map(|name| StaleFile {
+            removed: !kept.contains(&name),
+            name,
+        })
+        .collect())
+}
+
+/// A file we generated on a previous run, but no longer generate.
+#[derive(Debug)]
+pub struct StaleFile {
+    /// The file name, relative to the output directory.
+    pub name: String,
+    /// Whether the file was removed.
+    pub removed: bool,
+}
+
+/// Renders the client in memory, without touching the output
+/// directory.  This includes the manifest of the generated files.
+pub fn render_files(api: &Api, config: &RenderConfig) -> Result<Files, anyhow::Error> {
+    let mut files = Files::new();
+
+    if api.namespaces.contains_key(TYPES_MODULE) {
+        anyhow::bail!(
+            "there is a namespace named `{TYPES_MODULE}`, which would overwrite the shared types"
+        );
+    }
+    if api.namespaces.contains_key(SCOPES_MODULE) {
+        anyhow::bail!(
+            "there is a namespace named `{SCOPES_MODULE}`, which would overwrite the scope catalog"
+        );
+    }
+    if api.namespaces.contains_key(IDS_MODULE) {
+        anyhow::bail!(
+            "there is a namespace named `{IDS_MODULE}`, which would overwrite the kinds of IDs"
+        );
+    }
+    let scope_names = scopes::ScopeNames::new(config);
+    for name in api.ids.keys() {
+        if !api.scopes.is_empty() && scope_names.all().contains(&name.as_str()) {
+            anyhow::bail!(
+                "the kind of ID `{name}` has the same name as a type the scope catalog exports"
+            );
+        }
+    }
+    let mut names = HashMap::new();
+    for name in api.schemas.keys() {
+        let type_name = schema_name(config, name);
+        if let Some(other) = names.insert(type_name.clone(), name) {
+            anyhow::bail!(
+                "the schemas `{other}` and `{name}` would both generate the type `{type_name}`"
+            );
+        }
+        if !api.scopes.is_empty() && scope_names.all().contains(&type_name.as_str()) {
+            anyhow::bail!(
+                "the schema `{name}` would generate the type `{type_name}`, which the scope \
+                 catalog already exports"
+            );
+        }
+        if api.ids.contains_key(&type_name) {
+            anyhow::bail!(
+                "the schema `{name}` would generate the type `{type_name}`, which is already a \
+                 kind of ID"
+            );
+        }
+    }
+
+    if !api.ids.is_empty() {
+        let mut file = Vec::new();
         let mut render = Render {
             writer: &mut file,
-            api,
+            schemas: &api.schemas,
+            config,
             depth: 0,
         };
-        render.render(name, namespace).with_context(|| {
-            format!(
-                "when attempting to render the namespace `{}` to the output file in {}",
-                name,
-                path.display()
-            )
+        write_notice(&mut render.writer, &[ZOD_IMPORT])?;
+        render.render_ids(api).with_context(|| {
+            format!("when attempting to render the kinds of IDs to {IDS_MODULE}.ts")
         })?;
+        files.insert(format!("{IDS_MODULE}.ts"), file);
     }
 
-    let path = dir.join("index.ts");
-    let mut file = std::fs::File::create(&path).with_context(|| {
-        format!(
-            "when attempting to create the output file {}",
-            path.display()
-        )
-    })?;
+    if !api.schemas.is_empty() {
+        let mut file = Vec::new();
+        let mut render = Render {
+            writer: &mut file,
+            schemas: &api.schemas,
+            config,
+            depth: 0,
+        };
+        let mut ids = IndexSet::new();
+        for schema in api.schemas.values() {
+            schema.ty.ids(&mut ids);
+        }
+        write_file_notice(&mut render.writer, config, &ids_import(ids))?;
+        for (name, schema) in &api.schemas {
+            render.render_schema(name, schema).with_context(|| {
+                format!("when attempting to render the schema `{name}` to {TYPES_MODULE}.ts")
+            })?;
+        }
+        files.insert(format!("{TYPES_MODULE}.ts"), file);
+    }
+
+    if !api.scopes.is_empty() {
+        let mut file = Vec::new();
+        let mut render = Render {
+            writer: &mut file,
+            schemas: &api.schemas,
+            config,
+            depth: 0,
+        };
+        write_notice(&mut render.writer, &[])?;
+        render.render_scopes(api).with_context(|| {
+            format!("when attempting to render the scope catalog to {SCOPES_MODULE}.ts")
+        })?;
+        files.insert(format!("{SCOPES_MODULE}.ts"), file);
+    }
 
-    writeln!(file, "{FILE_NOTICE}")?;
-    for name in extract.namespaces.keys() {
+    for (name, namespace) in &api.namespaces {
+        let file_name = format!("{}.ts", name);
+        let mut file = Vec::new();
+        let mut render = Render {
+            writer: &mut file,
+            schemas: &api.schemas,
+            config,
+            depth: 0,
+        };
+        render.render(name, namespace).with_context(|| {
+            format!("when attempting to render the namespace `{name}` to {file_name}")
+        })?;
+        files.insert(file_name, file);
+    }
+
+    let mut file = Vec::new();
+    write_file_notice(&mut file, config, &[])?;
+    if !api.ids.is_empty() {
+        writeln!(file, "export * from \"./{IDS_MODULE}\";")?;
+    }
+    if !api.schemas.is_empty() {
+        writeln!(file, "export * from \"./{TYPES_MODULE}\";")?;
+    }
+    if !api.scopes.is_empty() {
+        writeln!(file, "export * from \"./{SCOPES_MODULE}\";")?;
+    }
+    for name in api.namespaces.keys() {
         writeln!(file, "export * from \"./{}\";", name)?;
     }
+    files.insert("index.ts".to_string(), file);
 
-    Ok(())
+    let manifest = crate::manifest::Manifest {
+        files: files.keys().cloned().collect(),
+    };
+    files.insert(
+        crate::manifest::MANIFEST_FILE.to_string(),
+        manifest.to_vec()?,
+    );
+
+    Ok(files)
 }
 
-pub struct Render<'o, W: std::io::Write> {
+/// Whether the given file was generated by us, judging by the notice
+/// at the top of it.
+pub fn is_generated(contents: &[u8]) -> bool {
+    String::from_utf8_lossy(contents)
+        .lines()
+        .take_while(|line| !line.starts_with("import"))
+        .any(|line| line.contains(GENERATED_MARKER))
+}
+
+pub(crate) struct Render<'o, W: std::io::Write> {
     writer: W,
-    api: &'o openapiv3::OpenAPI,
+    /// The named schemas, for when we need to look past a reference.
+    schemas: &'o IndexMap<String, Schema>,
+    config: &'o RenderConfig,
     depth: usize,
 }
 
@@ -69,11 +253,41 @@ impl<W> Render<'_, W>
 where
     W: std::io::Write,
 {
-    fn render(&mut self, name: &str, ns: &ExtractNamespace<'_>) -> Result<(), anyhow::Error> {
-        writeln!(self.writer, "{FILE_NOTICE}")?;
+    fn render(&mut self, name: &str, ns: &Namespace) -> Result<(), anyhow::Error> {
+        let mut refs = IndexSet::new();
+        let mut ids = IndexSet::new();
+        for method in &ns.methods {
+            for param in &method.parameters {
+                param.ty.refs(&mut refs);
+                param.ty.ids(&mut ids);
+            }
+            if let Some(response) = &method.response {
+                response.refs(&mut refs);
+                response.ids(&mut ids);
+            }
+        }
+        let mut imports = ids_import(ids);
+        if !refs.is_empty() {
+            let mut refs = refs
+                .into_iter()
+                .map(|name| schema_name(self.config, name))
+                .collect::<Vec<_>>();
+            refs.sort();
+            imports.push(format!(
+                "import {{ {} }} from \"./{TYPES_MODULE}\";",
+                refs.join(", ")
+            ));
+        }
+        if ns.methods.iter().any(|method| !method.errors.is_empty()) {
+            imports.push(format!(
+                "import {{ TwitchError }} from \"{}\";",
+                self.config.errors_module
+            ));
+        }
+        write_file_notice(&mut self.writer, self.config, &imports)?;
 
         for option in &ns.methods {
-            if option.has_active_params() {
+            if option.has_params() {
                 self.render_requests(option)?;
             }
         }
@@ -82,6 +296,10 @@ where
             self.render_response(ret)?;
         }
 
+        for method in &ns.methods {
+            self.render_errors(method)?;
+        }
+
         let class_name = name.to_upper_camel_case();
 
         writeln!(
@@ -99,25 +317,27 @@ where
         Ok(())
     }
 
-    fn render_requests(
-        &mut self,
-        method: &ExtractNamespaceMethod<'_>,
-    ) -> Result<(), anyhow::Error> {
+    fn render_requests(&mut self, method: &Method) -> Result<(), anyhow::Error> {
         let name = format!("{}Request", method.name).to_upper_camel_case();
         writeln!(self.writer, "export interface {name} {{")?;
 
-        for param in method.parameters.values() {
-            if param.kind.is_active() {
-                let q = if param.optional { "?" } else { "" };
-                let comment = wrap_comment(param.description.unwrap_or(""), 74);
-                writeln!(self.writer, "  /**")?;
-                for line in comment.trim_end().split('\n') {
-                    writeln!(self.writer, "   * {}", line)?;
+        for param in &method.parameters {
+            let q = if param.is_optional() { "?" } else { "" };
+            let mut description = param.description.clone().unwrap_or_default();
+            if param.kind.is_current_user() {
+                if !description.is_empty() {
+                    description.push_str("\n\n");
                 }
-                write!(self.writer, "   */\n  {name}{q}: ", name = param.id)?;
-                self.render_type(param.kind.is_token_id(), param.ty)?;
-                writeln!(self.writer, ",")?;
+                description.push_str("Defaults to the ID of the token's user.");
+            }
+            let comment = wrap_comment(&description, self.config.comment_width);
+            writeln!(self.writer, "  /**")?;
+            for line in comment.trim_end().split('\n') {
+                writeln!(self.writer, "   * {}", line)?;
             }
+            write!(self.writer, "   */\n  {name}{q}: ", name = param.id)?;
+            self.render_type(Side::Request, param.kind.is_token_id(), &param.ty)?;
+            writeln!(self.writer, ",")?;
         }
         writeln!(self.writer, "}}")?;
         Ok(())
@@ -141,8 +361,7 @@ fn wrap_comment(text: &str, max_line_length: usize) -> String {
         text.char_indices()
             .filter(|(_, c)| c.is_whitespace())
             .map(|(i, _)| i)
-            .filter(|&i| i <= cap)
-            .last()
+            .rfind(|&i| i <= cap)
     }
     fn unfold_line(text: &str, max_line_length: usize) -> VecDeque<&str> {
         let mut lines = VecDeque::<&str>::new();
@@ -178,6 +397,69 @@ fn wrap_comment(text: &str, max_line_length: usize) -> String {
         })
 }
 
+/// The name of the generated module with the shared types in it.
+const TYPES_MODULE: &str = "types";
+
+/// The name of the generated module with the scope catalog in it.
+const SCOPES_MODULE: &str = "scopes";
+
+/// The name of the generated module with the kinds of IDs in it.
+const IDS_MODULE: &str = "ids";
+
+/// The import of the given kinds of IDs, if there are any.
+fn ids_import(ids: IndexSet<&str>) -> Vec<String> {
+    if ids.is_empty() {
+        return Vec::new();
+    }
+    let mut ids = ids.into_iter().collect::<Vec<_>>();
+    ids.sort_unstable();
+    vec![format!(
+        "import {{ {} }} from \"./{IDS_MODULE}\";",
+        ids.join(", ")
+    )]
+}
+
+/// The name of the generated type for the named schema.
+fn schema_name(config: &RenderConfig, name: &str) -> String {
+    format!("{}{}", config.type_prefix, name.to_upper_camel_case())
+}
+
+/// Writes the notice at the top of every generated file, followed by
+/// the configured imports, and then any `extra` imports.
+fn write_file_notice<W: std::io::Write>(
+    writer: W,
+    config: &RenderConfig,
+    extra: &[String],
+) -> Result<(), anyhow::Error> {
+    let imports = config
+        .imports
+        .iter()
+        .chain(extra)
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    write_notice(writer, &imports)
+}
+
+/// Writes the notice at the top of a generated file followed by only
+/// the given imports, for the files that have no use for the
+/// configured ones.
+fn write_notice<W: std::io::Write>(mut writer: W, imports: &[&str]) -> Result<(), anyhow::Error> {
+    writeln!(writer, "{FILE_NOTICE}")?;
+    for import in imports {
+        writeln!(writer, "{import}")?;
+    }
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// The import of zod, for the files that use it but not the rest of
+/// the configured imports.
+const ZOD_IMPORT: &str = "import { z } from \"zod\";";
+
+/// The line in [`FILE_NOTICE`] that marks a file as generated.
+const GENERATED_MARKER: &str =
+    "This file is automatically generated. Do not edit this file directly.";
+
 const FILE_NOTICE: &str = r#"
 /******************************************************************************
  *
@@ -189,8 +471,4 @@ const FILE_NOTICE: &str = r#"
  * `scripts/twitch` directory of the repository.
  *
  *****************************************************************************/
-
-import type { Twitch } from "../index";
-import type { TokenId } from "../types";
-import { z } from "zod";
 "#;
@@ -1,37 +1,92 @@
-use crate::extract::ExtractNamespaceMethod;
-use heck::{ToLowerCamelCase as _, ToUpperCamelCase as _};
+use super::types::Side;
+use crate::ir::{AdditionalProperties, Method, ObjectType, Schema, Type};
+use heck::ToUpperCamelCase as _;
 
 impl<W> super::Render<'_, W>
 where
     W: std::io::Write,
 {
-    pub(super) fn render_response(
-        &mut self,
-        method: &ExtractNamespaceMethod<'_>,
-    ) -> Result<(), anyhow::Error> {
-        if let Some(ret) = &method.ret {
+    pub(super) fn render_response(&mut self, method: &Method) -> Result<(), anyhow::Error> {
+        if let Some(ret) = &method.response {
             let name = format!("{}Response", method.name).to_upper_camel_case();
             write!(self.writer, "export const {name} = ")?;
-            self.render_response_object(ret)?;
+            self.render_response_type(ret)?;
             writeln!(self.writer, ";")?;
+            self.write_inferred_type(&name, ret)?;
+        }
+        Ok(())
+    }
+
+    /// Renders a named schema, as both a zod schema and a type of
+    /// the same name.
+    pub(super) fn render_schema(
+        &mut self,
+        name: &str,
+        schema: &Schema,
+    ) -> Result<(), anyhow::Error> {
+        let name = super::schema_name(self.config, name);
+        self.write_schema_comment(schema.description.as_deref())?;
+        if schema.recursive {
+            return self.render_recursive_schema(&name, &schema.ty);
+        }
+        write!(self.writer, "export const {name} = ")?;
+        self.render_response_type(&schema.ty)?;
+        writeln!(self.writer, ";")?;
+        self.write_inferred_type(&name, &schema.ty)
+    }
+
+    /// Renders a schema that references itself.  The zod schema is
+    /// lazy, so that it can reference schemas that come after it; and
+    /// zod can't infer the type of such a schema, so it's written
+    /// out. What it parses is left as `unknown`, so a request
+    /// that sends it takes anything.
+    fn render_recursive_schema(&mut self, name: &str, ty: &Type) -> Result<(), anyhow::Error> {
+        write!(
+            self.writer,
+            "export const {name}: z.ZodType<{name}, z.ZodTypeDef, unknown> = z.lazy(() => "
+        )?;
+        self.render_response_type(ty)?;
+        writeln!(self.writer, ");")?;
+        if matches!(ty, Type::Object(_)) {
+            write!(self.writer, "export interface {name} ")?;
+            self.render_type(Side::Response, false, ty)?;
+            writeln!(self.writer, "\n")?;
+        } else {
+            write!(self.writer, "export type {name} = ")?;
+            self.render_type(Side::Response, false, ty)?;
+            writeln!(self.writer, ";\n")?;
+        }
+        Ok(())
+    }
+
+    /// Writes the type of the same name as a zod schema.  Only an
+    /// object can be extended by an interface; anything else gets an
+    /// alias.
+    fn write_inferred_type(&mut self, name: &str, ty: &Type) -> Result<(), anyhow::Error> {
+        if matches!(ty, Type::Object(_)) {
             writeln!(
                 self.writer,
                 "export interface {name} extends z.infer<typeof {name}> {{}}\n"
             )?;
+        } else {
+            writeln!(
+                self.writer,
+                "export type {name} = z.infer<typeof {name}>;\n"
+            )?;
         }
         Ok(())
     }
 
-    fn render_response_type(&mut self, ty: &openapiv3::Type) -> Result<(), anyhow::Error> {
+    fn render_response_type(&mut self, ty: &Type) -> Result<(), anyhow::Error> {
         match ty {
-            openapiv3::Type::String(s) => {
-                if s.enumeration.is_empty() {
+            Type::String { enumeration } => {
+                if enumeration.is_empty() {
                     write!(self.writer, "z.string()")?;
                     Ok(())
                 } else {
                     write!(self.writer, "z.enum([")?;
                     let mut first = true;
-                    for variant in s.enumeration.iter().flat_map(|v| v.as_deref()) {
+                    for variant in enumeration {
                         if first {
                             first = false;
                         } else {
@@ -43,48 +98,150 @@ where
                     Ok(())
                 }
             }
-            openapiv3::Type::Number(_) | openapiv3::Type::Integer(_) => {
+            Type::Number | Type::Integer => {
                 write!(self.writer, "z.number()")?;
                 Ok(())
             }
-            openapiv3::Type::Boolean(_) => {
+            Type::Boolean => {
                 write!(self.writer, "z.boolean()")?;
                 Ok(())
             }
-            openapiv3::Type::Array(v) => {
-                if let Some(item) = v
-                    .items
-                    .as_ref()
-                    .and_then(|item| crate::extract::resolve_reference_boxed_schema(item, self.api))
-                {
-                    let out = match &item.schema_kind {
-                        openapiv3::SchemaKind::Type(ty) => ty,
-                        o => unreachable!("array item type is not a supported type? {:?}", o),
-                    };
-                    self.render_response_type(out)?;
-                } else {
-                    write!(self.writer, "z.unknown()")?;
-                }
+            Type::Array { items } => {
+                self.render_response_type(items)?;
                 write!(self.writer, ".array()")?;
                 Ok(())
             }
-            openapiv3::Type::Object(obj) => {
+            Type::Object(obj) => {
                 self.render_response_object(obj)?;
                 Ok(())
             }
+            Type::Union {
+                variants,
+                discriminator,
+            } => self.render_response_union(variants, discriminator.as_deref()),
+            Type::Intersection { parts } => self.render_response_intersection(parts),
+            Type::Id { name } => {
+                write!(self.writer, "{name}")?;
+                Ok(())
+            }
+            Type::Ref { name } => {
+                write!(self.writer, "{}", super::schema_name(self.config, name))?;
+                Ok(())
+            }
+            Type::Nullable { inner } => {
+                self.render_response_type(inner)?;
+                write!(self.writer, ".nullable()")?;
+                Ok(())
+            }
+            Type::Unknown => {
+                write!(self.writer, "z.unknown()")?;
+                Ok(())
+            }
         }
     }
 
-    fn render_response_object(&mut self, obj: &openapiv3::ObjectType) -> Result<(), anyhow::Error> {
+    /// Renders `oneOf`/`anyOf`.  zod can only tell the variants apart
+    /// by the discriminator if every variant is a plain object (not
+    /// one we transform), with an enum for the discriminator; we fall
+    /// back to trying every variant otherwise.
+    fn render_response_union(
+        &mut self,
+        variants: &[Type],
+        discriminator: Option<&str>,
+    ) -> Result<(), anyhow::Error> {
+        match variants {
+            [] => {
+                write!(self.writer, "z.never()")?;
+                return Ok(());
+            }
+            [variant] => return self.render_response_type(variant),
+            _ => {}
+        }
+
+        let discriminated = discriminator.filter(|discriminator| {
+            variants.iter().all(|variant| {
+                self.plain_object(variant).is_some_and(|obj| {
+                    obj.properties.iter().any(|prop| {
+                        prop.name == *discriminator
+                            && !prop.optional
+                            && matches!(&prop.ty, Type::String { enumeration } if !enumeration.is_empty())
+                    })
+                })
+            })
+        });
+        match discriminated {
+            Some(discriminator) => write!(
+                self.writer,
+                "z.discriminatedUnion(\"{}\", [",
+                discriminator.escape_default()
+            )?,
+            None => write!(self.writer, "z.union([")?,
+        }
+        for (i, variant) in variants.iter().enumerate() {
+            if i > 0 {
+                write!(self.writer, ", ")?;
+            }
+            self.render_response_type(variant)?;
+        }
+        write!(self.writer, "])")?;
+        Ok(())
+    }
+
+    /// Renders `allOf`.  Plain objects can be merged into one object;
+    /// anything else has to be parsed as an intersection.
+    fn render_response_intersection(&mut self, parts: &[Type]) -> Result<(), anyhow::Error> {
+        match parts {
+            [] => {
+                write!(self.writer, "z.unknown()")?;
+                return Ok(());
+            }
+            [part] => return self.render_response_type(part),
+            _ => {}
+        }
+
+        if parts.iter().all(|part| self.plain_object(part).is_some()) {
+            self.render_response_type(&parts[0])?;
+            for part in &parts[1..] {
+                write!(self.writer, ".merge(")?;
+                self.render_response_type(part)?;
+                write!(self.writer, ")")?;
+            }
+        } else {
+            for _ in 1..parts.len() {
+                write!(self.writer, "z.intersection(")?;
+            }
+            self.render_response_type(&parts[0])?;
+            for part in &parts[1..] {
+                write!(self.writer, ", ")?;
+                self.render_response_type(part)?;
+                write!(self.writer, ")")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The object that the type renders as a `z.object` (rather than
+    /// something transformed), if it does.
+    fn plain_object<'t>(&'t self, ty: &'t Type) -> Option<&'t ObjectType> {
+        match ty {
+            Type::Object(obj) if !should_transform_properties(obj) => Some(obj),
+            // a lazy schema isn't a `z.object`, whatever it parses.
+            Type::Ref { name } => self
+                .schemas
+                .get(name)
+                .filter(|schema| !schema.recursive)
+                .and_then(|schema| self.plain_object(&schema.ty)),
+            _ => None,
+        }
+    }
+
+    fn render_response_object(&mut self, obj: &ObjectType) -> Result<(), anyhow::Error> {
         if !obj.properties.is_empty() {
             let transforms = should_transform_properties(obj);
             writeln!(self.writer, "z.object({{")?;
             self.depth += 1;
             let mut first = true;
-            for (name, prop) in obj.properties.iter() {
-                let ty = crate::extract::resolve_reference_boxed_schema(prop, self.api)
-                    .expect("property type is a $ref, but we could not resolve it");
-                let optional = !obj.required.contains(name);
+            for prop in &obj.properties {
                 if first {
                     first = false;
                 } else {
@@ -92,7 +249,7 @@ where
                 }
 
                 if !transforms {
-                    self.write_schema_comment(ty)?;
+                    self.write_schema_comment(prop.description.as_deref())?;
                 }
 
                 write!(
@@ -100,13 +257,11 @@ where
                     "{blank:depth$}\"{name}\": ",
                     blank = "",
                     depth = self.depth * 2,
+                    name = prop.name,
                 )?;
-                match &ty.schema_kind {
-                    openapiv3::SchemaKind::Type(ty) => self.render_response_type(ty)?,
-                    o => unreachable!("object property type is not a supported type? {:?}", o),
-                }
+                self.render_response_type(&prop.ty)?;
 
-                if optional {
+                if prop.optional {
                     write!(self.writer, ".optional()")?;
                 }
             }
@@ -119,16 +274,15 @@ where
                     depth = (self.depth - 1) * 2
                 )?;
 
-                for (name, prop) in obj.properties.iter() {
-                    let ty = crate::extract::resolve_reference_boxed_schema(prop, self.api)
-                        .expect("property type is a $ref, but we could not resolve it");
-                    self.write_schema_comment(ty)?;
+                for prop in &obj.properties {
+                    self.write_schema_comment(prop.description.as_deref())?;
                     writeln!(
                         self.writer,
                         "{blank:depth$}\"{id}\": it[\"{name}\"],",
                         blank = "",
                         depth = self.depth * 2,
-                        id = name.to_lower_camel_case(),
+                        id = prop.id,
+                        name = prop.name,
                     )?;
                 }
                 self.depth -= 1;
@@ -153,21 +307,12 @@ where
         }
 
         match obj.additional_properties.as_ref() {
-            Some(openapiv3::AdditionalProperties::Any(false)) | None => {}
-            Some(openapiv3::AdditionalProperties::Any(true)) => {
+            None => {}
+            Some(AdditionalProperties::Any) => {
                 writeln!(self.writer, ".catchall(z.unknown())")?;
             }
-            Some(openapiv3::AdditionalProperties::Schema(schema)) => {
-                let Some(schema) = crate::extract::resolve_reference_schema(schema, self.api)
-                else {
-                    unreachable!(
-                        "additional properties schema is a $ref, but we could not resolve it"
-                    )
-                };
-                let openapiv3::SchemaKind::Type(ty) = &schema.schema_kind else {
-                    unreachable!("additional properties schema is not a single type?")
-                };
-                self.write_schema_comment(schema)?;
+            Some(AdditionalProperties::Typed { description, ty }) => {
+                self.write_schema_comment(description.as_deref())?;
                 write!(self.writer, ".catchall(")?;
                 self.depth += 1;
                 self.render_response_type(ty)?;
@@ -180,6 +325,6 @@ where
     }
 }
 
-fn should_transform_properties(obj: &openapiv3::ObjectType) -> bool {
-    obj.properties.iter().any(|(name, _)| name.contains('_'))
+pub(super) fn should_transform_properties(obj: &ObjectType) -> bool {
+    obj.properties.iter().any(|prop| prop.name.contains('_'))
 }
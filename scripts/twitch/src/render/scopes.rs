@@ -0,0 +1,152 @@
+use crate::ir::Api;
+
+impl<W> super::Render<'_, W>
+where
+    W: std::io::Write,
+{
+    /// Renders the scope catalog: every scope, with its description;
+    /// the scopes every method requires; and `minimalScopes`, which
+    /// works out the fewest scopes needed to call a set of methods.
+    pub(super) fn render_scopes(&mut self, api: &Api) -> Result<(), anyhow::Error> {
+        let names = ScopeNames::new(self.config);
+
+        writeln!(
+            self.writer,
+            "/**\n * Every OAuth scope, with what it grants.\n */\nexport const {catalog} = {{",
+            catalog = names.catalog
+        )?;
+        for (scope, description) in &api.scopes {
+            writeln!(
+                self.writer,
+                "  \"{scope}\": \"{description}\",",
+                scope = scope.escape_default(),
+                description = description.as_deref().unwrap_or("").trim().escape_default()
+            )?;
+        }
+        writeln!(
+            self.writer,
+            "}} as const;\n\nexport type {scope} = keyof typeof {catalog};\n",
+            scope = names.scope,
+            catalog = names.catalog
+        )?;
+
+        writeln!(
+            self.writer,
+            "/**\n * The scopes each method requires, keyed by `namespace.method`. Each entry \
+             is\n * a set of scopes that are needed together; any one of the sets is enough. A\n \
+             * method without any sets requires no scopes at all.\n */\nexport const {methods} = \
+             {{",
+            methods = names.methods
+        )?;
+        for (namespace, ns) in &api.namespaces {
+            for method in &ns.methods {
+                let sets = method
+                    .requires_scopes
+                    .iter()
+                    .map(|set| {
+                        let scopes = set
+                            .iter()
+                            .map(|scope| format!("\"{}\"", scope.escape_default()))
+                            .collect::<Vec<_>>();
+                        format!("[{}]", scopes.join(", "))
+                    })
+                    .collect::<Vec<_>>();
+                writeln!(
+                    self.writer,
+                    "  \"{namespace}.{name}\": [{sets}],",
+                    name = method.name,
+                    sets = sets.join(", ")
+                )?;
+            }
+        }
+        writeln!(
+            self.writer,
+            "}} as const satisfies Record<string, readonly (readonly {scope}[])[]>;\n\nexport \
+             type {method} = keyof typeof {methods};\n",
+            scope = names.scope,
+            method = names.method,
+            methods = names.methods
+        )?;
+
+        writeln!(
+            self.writer,
+            "{}",
+            MINIMAL_SCOPES
+                .replace("$Scopes", &names.catalog)
+                .replace("$Scope", &names.scope)
+                .replace("$MethodScopes", &names.methods)
+                .replace("$Method", &names.method)
+        )?;
+        Ok(())
+    }
+}
+
+/// The names of what the scopes module exports, given the type
+/// prefix; e.g., `TwitchScope`.
+pub(super) struct ScopeNames {
+    pub(super) catalog: String,
+    pub(super) scope: String,
+    pub(super) methods: String,
+    pub(super) method: String,
+}
+
+impl ScopeNames {
+    pub(super) fn new(config: &crate::config::RenderConfig) -> Self {
+        let prefix = &config.type_prefix;
+        Self {
+            catalog: format!("{prefix}Scopes"),
+            scope: format!("{prefix}Scope"),
+            methods: format!("{prefix}MethodScopes"),
+            method: format!("{prefix}Method"),
+        }
+    }
+
+    pub(super) fn all(&self) -> [&str; 4] {
+        [&self.catalog, &self.scope, &self.methods, &self.method]
+    }
+}
+
+/// `minimalScopes`, with the names of the catalog (`$Scopes`,
+/// `$Scope`) and the method map (`$MethodScopes`, `$Method`) left to
+/// fill in.
+///
+/// Picking one set of scopes for each method, so that as few scopes
+/// as possible are needed overall, is a search; but few methods have
+/// more than one set to pick from, so it's a small one.
+const MINIMAL_SCOPES: &str = r#"/**
+ * Works out the fewest scopes that are needed to call every one of the given
+ * methods, in catalog order.
+ */
+export function minimalScopes(methods: Iterable<$Method>): $Scope[] {
+  const choices: (readonly (readonly $Scope[])[])[] = [];
+  for (const method of new Set(methods)) {
+    const sets: readonly (readonly $Scope[])[] = $MethodScopes[method];
+    if (sets.length > 0) {
+      choices.push(sets);
+    }
+  }
+
+  let best = null as Set<$Scope> | null;
+  const search = (index: number, chosen: Set<$Scope>) => {
+    if (best && chosen.size >= best.size) {
+      return;
+    }
+    if (index === choices.length) {
+      best = chosen;
+      return;
+    }
+    const sets = choices[index];
+    // if we already have one of the sets, picking another can't help.
+    if (sets.some((set) => set.every((scope) => chosen.has(scope)))) {
+      search(index + 1, chosen);
+      return;
+    }
+    for (const set of sets) {
+      search(index + 1, new Set([...chosen, ...set]));
+    }
+  };
+  search(0, new Set());
+
+  const scopes = Object.keys($Scopes) as $Scope[];
+  return scopes.filter((scope) => best?.has(scope));
+}"#;
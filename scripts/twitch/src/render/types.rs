@@ -1,5 +1,17 @@
+use super::response::should_transform_properties;
 use super::Render;
-use heck::ToLowerCamelCase as _;
+use crate::ir::{AdditionalProperties, ObjectType, Type};
+
+/// Which side of a request a TypeScript type is written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Side {
+    /// What the caller passes in, which is sent as it is; so, the
+    /// shape it has on the wire.
+    Request,
+    /// What a response is parsed into, with the fields renamed the
+    /// way the zod schema renames them.
+    Response,
+}
 
 impl<W> Render<'_, W>
 where
@@ -7,65 +19,69 @@ where
 {
     pub(super) fn render_type(
         &mut self,
+        side: Side,
         is_token_id: bool,
-        ty: &openapiv3::Type,
+        ty: &Type,
     ) -> Result<(), anyhow::Error> {
+        if let Type::Nullable { inner } = ty {
+            self.render_type(side, is_token_id, inner)?;
+            write!(self.writer, " | null")?;
+            return Ok(());
+        }
+
+        // user IDs are plain strings, whatever the spec says about
+        // them; unless we know them to be IDs.
         if is_token_id {
-            if matches!(ty, openapiv3::Type::Array(_)) {
-                write!(self.writer, "string[]")?;
-                return Ok(());
-            } else if matches!(ty, openapiv3::Type::String(_)) {
-                write!(self.writer, "string")?;
-                return Ok(());
-            } else {
-                unreachable!("unexpected base type {ty:?} for user token")
+            match ty {
+                Type::Id { .. } => {}
+                Type::Array { items } if matches!(**items, Type::Id { .. }) => {}
+                Type::Array { .. } => {
+                    write!(self.writer, "string[]")?;
+                    return Ok(());
+                }
+                Type::String { .. } => {
+                    write!(self.writer, "string")?;
+                    return Ok(());
+                }
+                _ => unreachable!("unexpected base type {ty:?} for user token"),
             }
         }
 
         match ty {
-            openapiv3::Type::String(s) => {
-                if s.enumeration.is_empty() {
+            Type::String { enumeration } => {
+                if enumeration.is_empty() {
                     write!(self.writer, "string")?;
                     Ok(())
                 } else {
-                    for variant in s.enumeration.iter().flat_map(|v| v.as_deref()) {
+                    for variant in enumeration {
                         write!(self.writer, "| \"{}\"", variant.escape_default())?;
                     }
                     Ok(())
                 }
             }
-            openapiv3::Type::Number(_) => {
-                write!(self.writer, "number")?;
-                Ok(())
-            }
-            openapiv3::Type::Integer(_) => {
+            Type::Number | Type::Integer => {
                 write!(self.writer, "number")?;
                 Ok(())
             }
-            openapiv3::Type::Boolean(_) => {
+            Type::Boolean => {
                 write!(self.writer, "boolean")?;
                 Ok(())
             }
-            openapiv3::Type::Array(v) => {
-                if let Some(item) = v
-                    .items
-                    .as_ref()
-                    .and_then(|item| crate::extract::resolve_reference_boxed_schema(item, self.api))
-                {
-                    let out = match &item.schema_kind {
-                        openapiv3::SchemaKind::Type(ty) => ty,
-                        o => unreachable!("array item type is not a supported type? {:?}", o),
-                    };
-                    self.render_type(false, out)?;
+            Type::Array { items } => {
+                // `T | null[]` would be an array of nulls.
+                if items.is_nullable() {
+                    write!(self.writer, "(")?;
+                    self.render_type(side, false, items)?;
+                    write!(self.writer, ")[]")?;
                 } else {
-                    write!(self.writer, "unknown")?;
+                    self.render_type(side, false, items)?;
+                    write!(self.writer, "[]")?;
                 }
-                write!(self.writer, "[]")?;
                 Ok(())
             }
-            openapiv3::Type::Object(obj) => {
+            Type::Object(obj) => {
                 writeln!(self.writer, "{{")?;
-                self.render_object_type(obj)?;
+                self.render_object_type(side, obj)?;
                 write!(
                     self.writer,
                     "\n{blank:depth$}}}",
@@ -74,19 +90,69 @@ where
                 )?;
                 Ok(())
             }
+            Type::Union { variants, .. } => {
+                self.render_composed_type(side, variants, " | ", "never")
+            }
+            Type::Intersection { parts } => {
+                self.render_composed_type(side, parts, " & ", "unknown")
+            }
+            Type::Id { name } => {
+                write!(self.writer, "{name}")?;
+                Ok(())
+            }
+            // the type of the same name is what the schema parses
+            // into, not what it parses.
+            Type::Ref { name } => {
+                let name = super::schema_name(self.config, name);
+                match side {
+                    Side::Request => write!(self.writer, "z.input<typeof {name}>")?,
+                    Side::Response => write!(self.writer, "{name}")?,
+                }
+                Ok(())
+            }
+            Type::Nullable { .. } => unreachable!("nullable types are rendered above"),
+            Type::Unknown => {
+                write!(self.writer, "unknown")?;
+                Ok(())
+            }
         }
     }
 
+    fn render_composed_type(
+        &mut self,
+        side: Side,
+        types: &[Type],
+        separator: &str,
+        empty: &str,
+    ) -> Result<(), anyhow::Error> {
+        if types.is_empty() {
+            write!(self.writer, "{empty}")?;
+            return Ok(());
+        }
+        write!(self.writer, "(")?;
+        for (i, ty) in types.iter().enumerate() {
+            if i > 0 {
+                write!(self.writer, "{separator}")?;
+            }
+            self.render_type(side, false, ty)?;
+        }
+        write!(self.writer, ")")?;
+        Ok(())
+    }
+
     pub(super) fn render_object_type(
         &mut self,
-        obj: &openapiv3::ObjectType,
+        side: Side,
+        obj: &ObjectType,
     ) -> Result<(), anyhow::Error> {
+        let transforms = side == Side::Response && should_transform_properties(obj);
         self.depth += 1;
         let mut first = true;
-        for (name, prop) in obj.properties.iter() {
-            let ty = crate::extract::resolve_reference_boxed_schema(prop, self.api)
-                .expect("property type is a $ref, but we could not resolve it");
-            let optional = !obj.required.contains(name);
+        for prop in &obj.properties {
+            // zod types an `unknown` field as optional, as
+            // `undefined` is one of its values.
+            let optional = prop.optional
+                || (side == Side::Response && !transforms && matches!(prop.ty, Type::Unknown));
             let q = if optional { "?" } else { "" };
             if first {
                 first = false;
@@ -94,49 +160,44 @@ where
                 writeln!(self.writer, ",")?;
             }
 
-            self.write_schema_comment(ty)?;
+            self.write_schema_comment(prop.description.as_deref())?;
 
             write!(
                 self.writer,
                 "{blank:depth$}{name}{q}: ",
                 blank = "",
                 depth = self.depth * 2,
-                name = name.to_lower_camel_case(),
+                name = PropertyName(if transforms { &prop.id } else { &prop.name }),
             )?;
-            match &ty.schema_kind {
-                openapiv3::SchemaKind::Type(ty) => self.render_type(false, ty)?,
-                o => unreachable!("object property type is not a supported type? {:?}", o),
-            }
+            self.render_type(side, false, &prop.ty)?;
         }
 
-        match obj.additional_properties.as_ref() {
-            Some(openapiv3::AdditionalProperties::Any(false)) | None => {}
-            Some(openapiv3::AdditionalProperties::Any(true)) => {
-                writeln!(
-                    self.writer,
-                    "{blank:depth$}[_ in string]: unknown, ",
-                    blank = "",
-                    depth = self.depth * 2
-                )?;
+        // the transform only keeps the fields it knows of; and the
+        // other fields need not be of the same type as the extra ones,
+        // which an index signature would insist on.
+        let extra = match obj.additional_properties.as_ref() {
+            None => None,
+            Some(_) if transforms => None,
+            Some(_) if side == Side::Response => Some((None, None)),
+            Some(AdditionalProperties::Any) => Some((None, None)),
+            Some(AdditionalProperties::Typed { description, ty }) => {
+                Some((description.as_deref(), Some(ty)))
             }
-            Some(openapiv3::AdditionalProperties::Schema(schema)) => {
-                let Some(schema) = crate::extract::resolve_reference_schema(schema, self.api)
-                else {
-                    unreachable!(
-                        "additional properties schema is a $ref, but we could not resolve it"
-                    )
-                };
-                let openapiv3::SchemaKind::Type(ty) = &schema.schema_kind else {
-                    unreachable!("additional properties schema is not a single type?")
-                };
-                self.write_schema_comment(schema)?;
-                write!(
-                    self.writer,
-                    "{blank:depth$}[_: string]: ",
-                    blank = "",
-                    depth = self.depth * 2
-                )?;
-                self.render_type(false, ty)?;
+        };
+        if let Some((description, ty)) = extra {
+            if !first {
+                writeln!(self.writer, ",")?;
+            }
+            self.write_schema_comment(description)?;
+            write!(
+                self.writer,
+                "{blank:depth$}[_: string]: ",
+                blank = "",
+                depth = self.depth * 2
+            )?;
+            match ty {
+                Some(ty) => self.render_type(side, false, ty)?,
+                None => write!(self.writer, "unknown")?,
             }
         }
 
@@ -146,10 +207,12 @@ where
 
     pub(super) fn write_schema_comment(
         &mut self,
-        schema: &openapiv3::Schema,
+        description: Option<&str>,
     ) -> Result<(), anyhow::Error> {
-        if let Some(desc) = schema.schema_data.description.as_deref() {
-            let comment = super::wrap_comment(desc, 71usize.saturating_sub(self.depth * 2));
+        if let Some(desc) = description {
+            // the comment markers take up three columns on their own.
+            let width = self.config.comment_width.saturating_sub(3);
+            let comment = super::wrap_comment(desc, width.saturating_sub(self.depth * 2));
             writeln!(
                 self.writer,
                 "{blank:depth$}/**",
@@ -176,3 +239,20 @@ where
         Ok(())
     }
 }
+
+/// The name of a property, quoted if it isn't an identifier.
+struct PropertyName<'n>(&'n str);
+
+impl std::fmt::Display for PropertyName<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let identifier =
+            self.0.chars().enumerate().all(|(i, c)| {
+                c == '_' || c == '$' || c.is_alphabetic() || (i > 0 && c.is_numeric())
+            });
+        if identifier && !self.0.is_empty() {
+            f.write_str(self.0)
+        } else {
+            write!(f, "\"{}\"", self.0.escape_default())
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Corrections to the OAuth scopes that the spec says each operation
+//! requires.
+//!
+//! The spec is often wrong about scopes: it lists scopes that are
+//! only needed for part of a response (`user:read:email` for
+//! `get-users`), and leaves out scopes that are.  The scopes file,
+//! `scopes.toml`, is keyed by operationId, and either replaces what
+//! the spec says, or adds to it:
+//!
+//! ```toml
+//! # the spec is wrong; only these will do.
+//! [get-chatters]
+//! any-of = [["moderator:read:chatters"], ["moderator:manage:chatters"]]
+//!
+//! # the spec is right, but there's another way in, too.
+//! [get-banned-users]
+//! also = [["moderator:manage:banned_users"]]
+//!
+//! # the spec is wrong; no scopes are needed at all.
+//! [get-users]
+//! any-of = []
+//! ```
+//!
+//! In both cases, each entry is one set of scopes that are needed
+//! together; having any one of the sets is enough.  This is what
+//! OpenAPI's `security` means, too.
+
+use anyhow::Context as _;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// The scopes file, relative to the root of the repository.
+pub const DEFAULT_SCOPES_FILE: &str = "./scripts/twitch/scopes.toml";
+
+/// The corrections in the scopes file, keyed by operationId.
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(transparent)]
+pub struct ScopeOverrides {
+    pub operations: BTreeMap<String, ScopeOverride>,
+}
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ScopeOverride {
+    /// The sets of scopes, any one of which is enough, instead of
+    /// what the spec says.  An empty list means no scopes are needed.
+    pub any_of: Option<Vec<Vec<String>>>,
+    /// More sets of scopes, any one of which is enough, on top of
+    /// what the spec (or `any-of`) says.
+    pub also: Vec<Vec<String>>,
+}
+
+impl ScopeOverrides {
+    /// Loads the scopes file.  Unlike the configuration file, we may
+    /// not have one at all; then, there are no corrections.
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("when reading the scopes file {}", path.display()))?;
+        let this: Self = toml::from_str(&contents)
+            .with_context(|| format!("when parsing the scopes file {}", path.display()))?;
+        this.validate()
+            .with_context(|| format!("in the scopes file {}", path.display()))?;
+        Ok(this)
+    }
+
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        for (id, op) in &self.operations {
+            let scopes = op.any_of.iter().flatten().chain(&op.also).flatten();
+            for scope in scopes {
+                if scope.is_empty() || scope.contains(char::is_whitespace) {
+                    anyhow::bail!("`{id}` has the scope `{scope}`, which is not a valid scope");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The scopes the operation requires, as sets of scopes, any one
+    /// of which is enough; given what the spec says it requires.
+    ///
+    /// If any of the sets is empty, no scopes are needed at all, and
+    /// so there are no sets.
+    pub fn resolve<'o>(&'o self, operation_id: &str, spec: Vec<Vec<&'o str>>) -> Vec<Vec<&'o str>> {
+        let op = self.operations.get(operation_id);
+        let mut sets = match op.and_then(|op| op.any_of.as_ref()) {
+            Some(any_of) => any_of
+                .iter()
+                .map(|set| set.iter().map(String::as_str).collect())
+                .collect(),
+            None => spec,
+        };
+        sets.extend(
+            op.into_iter()
+                .flat_map(|op| &op.also)
+                .map(|set| set.iter().map(String::as_str).collect::<Vec<_>>()),
+        );
+
+        if sets.iter().any(Vec::is_empty) {
+            return Vec::new();
+        }
+        let mut resolved = Vec::<Vec<&str>>::new();
+        for set in sets {
+            let mut unique = Vec::new();
+            for scope in set {
+                if !unique.contains(&scope) {
+                    unique.push(scope);
+                }
+            }
+            if !resolved.contains(&unique) {
+                resolved.push(unique);
+            }
+        }
+        resolved
+    }
+}
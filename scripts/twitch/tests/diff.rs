@@ -0,0 +1,604 @@
+//! Which changes to the generated client are breaking.
+//!
+//! Every test compares two versions of a single method,
+//! `users.getUsers`, that differ in one way.  Whether a change is
+//! breaking often depends on which way the data flows, so most
+//! changes are tried both in a parameter (a request) and in the
+//! response.
+
+use indexmap::IndexMap;
+use twitch::diff::{Bump, ChangeKind};
+use twitch::extract::ParamKind;
+use twitch::ir::{
+    AdditionalProperties, Api, Method, Namespace, ObjectType, Param, Property, Schema, Type,
+};
+
+fn api(method: Method) -> Api {
+    Api {
+        namespaces: IndexMap::from([(
+            "users".to_string(),
+            Namespace {
+                methods: vec![method],
+            },
+        )]),
+        schemas: IndexMap::new(),
+        scopes: IndexMap::new(),
+        ids: IndexMap::new(),
+    }
+}
+
+fn method(parameters: Vec<Param>, response: Option<Type>) -> Method {
+    Method {
+        name: "getUsers".to_string(),
+        operation_id: "get-users".to_string(),
+        path: "/users".to_string(),
+        method: "get".to_string(),
+        description: None,
+        parameters,
+        response,
+        statuses: vec![200],
+        may_be_empty: false,
+        download: false,
+        errors: Vec::new(),
+        requires_scopes: Vec::new(),
+    }
+}
+
+fn param(name: &str, optional: bool, ty: Type) -> Param {
+    token_param(name, optional, ParamKind::Query, ty)
+}
+
+fn token_param(name: &str, optional: bool, kind: ParamKind, ty: Type) -> Param {
+    Param {
+        id: name.to_string(),
+        name: name.to_string(),
+        description: None,
+        optional,
+        kind,
+        ty,
+    }
+}
+
+fn string() -> Type {
+    Type::String {
+        enumeration: Vec::new(),
+    }
+}
+
+fn enumeration(variants: &[&str]) -> Type {
+    Type::String {
+        enumeration: variants.iter().map(|v| v.to_string()).collect(),
+    }
+}
+
+fn id(name: &str) -> Type {
+    Type::Id {
+        name: name.to_string(),
+    }
+}
+
+fn nullable(ty: Type) -> Type {
+    Type::Nullable {
+        inner: Box::new(ty),
+    }
+}
+
+/// An object with the given fields, as `(name, optional, type)`.
+fn object(fields: &[(&str, bool, Type)]) -> Type {
+    Type::Object(ObjectType {
+        properties: fields
+            .iter()
+            .map(|(name, optional, ty)| Property {
+                id: name.to_string(),
+                name: name.to_string(),
+                description: None,
+                optional: *optional,
+                ty: ty.clone(),
+            })
+            .collect(),
+        additional_properties: None,
+    })
+}
+
+/// The changes between the two versions of the method, as
+/// `(location, kind, breaking)`.
+fn changes(old: Method, new: Method) -> Vec<(String, ChangeKind, bool)> {
+    twitch::diff::diff(&api(old), &api(new))
+        .into_iter()
+        .map(|change| (change.location, change.kind, change.breaking))
+        .collect()
+}
+
+/// The changes from a parameter of type `old` to one of type `new`.
+fn request(old: Type, new: Type) -> Vec<(String, ChangeKind, bool)> {
+    changes(
+        method(vec![param("p", false, old)], None),
+        method(vec![param("p", false, new)], None),
+    )
+}
+
+/// The changes from a response of type `old` to one of type `new`.
+fn response(old: Type, new: Type) -> Vec<(String, ChangeKind, bool)> {
+    changes(method(Vec::new(), Some(old)), method(Vec::new(), Some(new)))
+}
+
+fn change(location: &str, kind: ChangeKind, breaking: bool) -> Vec<(String, ChangeKind, bool)> {
+    vec![(location.to_string(), kind, breaking)]
+}
+
+const PARAM: &str = "users.getUsers(p)";
+const RESPONSE: &str = "users.getUsers.response";
+
+#[test]
+fn identical_apis_have_no_changes() {
+    let method = || method(vec![param("p", false, string())], Some(string()));
+    assert_eq!(changes(method(), method()), []);
+    assert_eq!(twitch::diff::bump(&[]), Bump::None);
+}
+
+#[test]
+fn methods_and_namespaces() {
+    let old = api(method(Vec::new(), None));
+    let mut new = old.clone();
+    new.namespaces["users"].methods[0].name = "getUser".to_string();
+    new.namespaces.insert(
+        "clips".to_string(),
+        Namespace {
+            methods: Vec::new(),
+        },
+    );
+
+    let found = twitch::diff::diff(&old, &new)
+        .into_iter()
+        .map(|change| (change.location, change.kind, change.breaking))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [
+            ("clips".to_string(), ChangeKind::NamespaceAdded, false),
+            ("users.getUser".to_string(), ChangeKind::MethodAdded, false),
+            (
+                "users.getUsers".to_string(),
+                ChangeKind::MethodRemoved,
+                true
+            ),
+        ]
+    );
+
+    let found = twitch::diff::diff(&new, &old)
+        .into_iter()
+        .map(|change| (change.kind, change.breaking))
+        .collect::<Vec<_>>();
+    assert!(found.contains(&(ChangeKind::NamespaceRemoved, true)));
+}
+
+#[test]
+fn params_added_and_removed() {
+    let none = method(Vec::new(), None);
+    let required = method(vec![param("p", false, string())], None);
+    let optional = method(vec![param("p", true, string())], None);
+
+    assert_eq!(
+        changes(none.clone(), required.clone()),
+        change(PARAM, ChangeKind::ParamAdded, true)
+    );
+    assert_eq!(
+        changes(none.clone(), optional.clone()),
+        change(PARAM, ChangeKind::ParamAdded, false)
+    );
+    assert_eq!(
+        changes(optional.clone(), none),
+        change(PARAM, ChangeKind::ParamRemoved, true)
+    );
+    assert_eq!(
+        changes(optional.clone(), required.clone()),
+        change(PARAM, ChangeKind::ParamRequired, true)
+    );
+    assert_eq!(
+        changes(required, optional),
+        change(PARAM, ChangeKind::ParamOptional, false)
+    );
+}
+
+#[test]
+fn current_user_params_are_optional() {
+    // filled in with the token's user, if left out.
+    let current = method(
+        vec![token_param(
+            "p",
+            false,
+            ParamKind::QueryCurrentUserToken,
+            string(),
+        )],
+        None,
+    );
+    let required = method(
+        vec![token_param("p", false, ParamKind::QueryUserToken, string())],
+        None,
+    );
+
+    assert_eq!(
+        changes(method(Vec::new(), None), current.clone()),
+        change(PARAM, ChangeKind::ParamAdded, false)
+    );
+    assert_eq!(
+        changes(current, required),
+        change(PARAM, ChangeKind::ParamRequired, true)
+    );
+}
+
+#[test]
+fn fields_added_and_removed() {
+    let none = object(&[]);
+    let required = object(&[("f", false, string())]);
+    let optional = object(&[("f", true, string())]);
+    let request_field = "users.getUsers(p).f";
+    let response_field = "users.getUsers.response.f";
+
+    // the caller has to pass in a required field, but an optional
+    // one can be left out.
+    assert_eq!(
+        request(none.clone(), required.clone()),
+        change(request_field, ChangeKind::FieldAdded, true)
+    );
+    assert_eq!(
+        request(none.clone(), optional.clone()),
+        change(request_field, ChangeKind::FieldAdded, false)
+    );
+    // a caller that still passes it in gets an excess property
+    // error.
+    assert_eq!(
+        request(optional.clone(), none.clone()),
+        change(request_field, ChangeKind::FieldRemoved, true)
+    );
+    assert_eq!(
+        request(optional.clone(), required.clone()),
+        change(request_field, ChangeKind::FieldRequired, true)
+    );
+    assert_eq!(
+        request(required.clone(), optional.clone()),
+        change(request_field, ChangeKind::FieldOptional, false)
+    );
+
+    // new fields in a response are never in the way, but a field the
+    // caller reads may no longer be there.
+    assert_eq!(
+        response(none.clone(), required.clone()),
+        change(response_field, ChangeKind::FieldAdded, false)
+    );
+    assert_eq!(
+        response(none.clone(), optional.clone()),
+        change(response_field, ChangeKind::FieldAdded, false)
+    );
+    assert_eq!(
+        response(optional.clone(), none),
+        change(response_field, ChangeKind::FieldRemoved, true)
+    );
+    assert_eq!(
+        response(optional.clone(), required.clone()),
+        change(response_field, ChangeKind::FieldRequired, false)
+    );
+    assert_eq!(
+        response(required, optional),
+        change(response_field, ChangeKind::FieldOptional, true)
+    );
+}
+
+#[test]
+fn additional_properties() {
+    let with = |additional| {
+        Type::Object(ObjectType {
+            properties: Vec::new(),
+            additional_properties: additional,
+        })
+    };
+    let none = with(None);
+    let any = with(Some(AdditionalProperties::Any));
+    let typed = |ty: Type| {
+        with(Some(AdditionalProperties::Typed {
+            description: None,
+            ty: Box::new(ty),
+        }))
+    };
+
+    assert_eq!(
+        request(none.clone(), any.clone()),
+        change(PARAM, ChangeKind::AdditionalPropertiesAdded, false)
+    );
+    assert_eq!(
+        request(any.clone(), none.clone()),
+        change(PARAM, ChangeKind::AdditionalPropertiesRemoved, true)
+    );
+    assert_eq!(
+        response(none.clone(), any.clone()),
+        change(RESPONSE, ChangeKind::AdditionalPropertiesAdded, true)
+    );
+    assert_eq!(
+        response(any, none),
+        change(RESPONSE, ChangeKind::AdditionalPropertiesRemoved, false)
+    );
+
+    // the other fields are compared as any other type is.
+    assert_eq!(
+        response(typed(string()), typed(nullable(string()))),
+        change(
+            "users.getUsers.response[string]",
+            ChangeKind::NullableAdded,
+            true
+        )
+    );
+}
+
+#[test]
+fn success_statuses() {
+    let with = |statuses: &[u16]| {
+        let mut method = method(Vec::new(), None);
+        method.statuses = statuses.to_vec();
+        method
+    };
+
+    // the client throws for any status it doesn't expect.
+    assert_eq!(
+        changes(with(&[200]), with(&[200, 202])),
+        change("users.getUsers", ChangeKind::StatusAdded, false)
+    );
+    assert_eq!(
+        changes(with(&[200, 202]), with(&[200])),
+        change("users.getUsers", ChangeKind::StatusRemoved, true)
+    );
+}
+
+#[test]
+fn enum_variants() {
+    let ab = enumeration(&["a", "b"]);
+    let abc = enumeration(&["a", "b", "c"]);
+
+    assert_eq!(
+        request(ab.clone(), abc.clone()),
+        change(PARAM, ChangeKind::EnumVariantAdded, false)
+    );
+    assert_eq!(
+        request(abc.clone(), ab.clone()),
+        change(PARAM, ChangeKind::EnumVariantRemoved, true)
+    );
+    assert_eq!(
+        response(ab.clone(), abc.clone()),
+        change(RESPONSE, ChangeKind::EnumVariantAdded, true)
+    );
+    assert_eq!(
+        response(abc, ab),
+        change(RESPONSE, ChangeKind::EnumVariantRemoved, false)
+    );
+}
+
+#[test]
+fn enums_and_strings() {
+    // an enum is a narrower string.
+    let ab = enumeration(&["a", "b"]);
+
+    assert_eq!(
+        request(ab.clone(), string()),
+        change(PARAM, ChangeKind::TypeChanged, false)
+    );
+    assert_eq!(
+        request(string(), ab.clone()),
+        change(PARAM, ChangeKind::TypeChanged, true)
+    );
+    assert_eq!(
+        response(ab.clone(), string()),
+        change(RESPONSE, ChangeKind::TypeChanged, true)
+    );
+    assert_eq!(
+        response(string(), ab),
+        change(RESPONSE, ChangeKind::TypeChanged, false)
+    );
+}
+
+#[test]
+fn nullability() {
+    assert_eq!(
+        request(string(), nullable(string())),
+        change(PARAM, ChangeKind::NullableAdded, false)
+    );
+    assert_eq!(
+        request(nullable(string()), string()),
+        change(PARAM, ChangeKind::NullableRemoved, true)
+    );
+    assert_eq!(
+        response(string(), nullable(string())),
+        change(RESPONSE, ChangeKind::NullableAdded, true)
+    );
+    assert_eq!(
+        response(nullable(string()), string()),
+        change(RESPONSE, ChangeKind::NullableRemoved, false)
+    );
+
+    // the type inside is still compared.
+    assert_eq!(
+        response(nullable(string()), nullable(Type::Number)),
+        change(RESPONSE, ChangeKind::TypeChanged, true)
+    );
+}
+
+#[test]
+fn id_widening() {
+    // an ID is a narrower string, too; and one kind of ID is not
+    // another.
+    assert_eq!(
+        request(string(), id("UserId")),
+        change(PARAM, ChangeKind::TypeChanged, true)
+    );
+    assert_eq!(
+        request(id("UserId"), string()),
+        change(PARAM, ChangeKind::TypeChanged, false)
+    );
+    assert_eq!(
+        response(string(), id("UserId")),
+        change(RESPONSE, ChangeKind::TypeChanged, false)
+    );
+    assert_eq!(
+        response(id("UserId"), string()),
+        change(RESPONSE, ChangeKind::TypeChanged, true)
+    );
+    assert_eq!(
+        request(id("UserId"), id("GameId")),
+        change(PARAM, ChangeKind::TypeChanged, true)
+    );
+    assert_eq!(
+        response(id("UserId"), id("GameId")),
+        change(RESPONSE, ChangeKind::TypeChanged, true)
+    );
+}
+
+#[test]
+fn user_token_params_are_strings_unless_ids() {
+    // whatever the spec says, a user ID is rendered as a string.
+    let user = |ty| {
+        method(
+            vec![token_param("p", false, ParamKind::QueryUserToken, ty)],
+            None,
+        )
+    };
+    assert_eq!(changes(user(string()), user(enumeration(&["a"]))), []);
+    assert_eq!(
+        changes(user(string()), user(id("UserId"))),
+        change(PARAM, ChangeKind::TypeChanged, true)
+    );
+}
+
+#[test]
+fn type_changes_and_arrays() {
+    assert_eq!(
+        request(string(), Type::Number),
+        change(PARAM, ChangeKind::TypeChanged, true)
+    );
+    assert_eq!(
+        response(Type::Number, Type::Boolean),
+        change(RESPONSE, ChangeKind::TypeChanged, true)
+    );
+    let array = |ty| Type::Array {
+        items: Box::new(ty),
+    };
+    assert_eq!(
+        response(array(string()), array(nullable(string()))),
+        change("users.getUsers.response[]", ChangeKind::NullableAdded, true)
+    );
+}
+
+#[test]
+fn responses_added_removed_and_emptied() {
+    assert_eq!(
+        changes(method(Vec::new(), None), method(Vec::new(), Some(string()))),
+        change(RESPONSE, ChangeKind::TypeChanged, true)
+    );
+    assert_eq!(
+        changes(method(Vec::new(), Some(string())), method(Vec::new(), None)),
+        change(RESPONSE, ChangeKind::TypeChanged, true)
+    );
+
+    let mut empty = method(Vec::new(), Some(string()));
+    empty.may_be_empty = true;
+    assert_eq!(
+        changes(method(Vec::new(), Some(string())), empty.clone()),
+        change(RESPONSE, ChangeKind::NullableAdded, true)
+    );
+    assert_eq!(
+        changes(empty, method(Vec::new(), Some(string()))),
+        change(RESPONSE, ChangeKind::NullableRemoved, false)
+    );
+}
+
+#[test]
+fn bump_follows_the_most_breaking_change() {
+    let optional = method(vec![param("p", true, string())], None);
+    let required = method(vec![param("p", false, string())], None);
+
+    let minor = twitch::diff::diff(&api(required.clone()), &api(optional.clone()));
+    assert_eq!(twitch::diff::bump(&minor), Bump::Minor);
+    let major = twitch::diff::diff(&api(optional), &api(required));
+    assert_eq!(twitch::diff::bump(&major), Bump::Major);
+}
+
+#[test]
+fn recursive_schemas_are_compared_by_their_contents() {
+    // a user, with the users they follow.
+    let user = |id: Type| {
+        let mut api = api(method(
+            Vec::new(),
+            Some(Type::Ref {
+                name: "User".to_string(),
+            }),
+        ));
+        let follows = Type::Array {
+            items: Box::new(Type::Ref {
+                name: "User".to_string(),
+            }),
+        };
+        api.schemas.insert(
+            "User".to_string(),
+            Schema {
+                description: None,
+                ty: object(&[("id", false, id), ("follows", false, follows)]),
+                recursive: true,
+            },
+        );
+        api
+    };
+
+    let found = twitch::diff::diff(&user(string()), &user(string()));
+    assert!(found.is_empty(), "{found:?}");
+    let found = twitch::diff::diff(&user(string()), &user(Type::Number))
+        .into_iter()
+        .map(|change| (change.location, change.kind, change.breaking))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        change("users.getUsers.response.id", ChangeKind::TypeChanged, true)
+    );
+}
+
+/// The fixture spec, with the patches applied.
+fn fixture() -> twitch::openapiv3::OpenAPI {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+    twitch::file::load(&twitch::file::LoadOptions {
+        path: Some(dir.join("tests/fixtures/openapi.json")),
+        patches: Some(dir.join("patches")),
+        frozen: true,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+#[test]
+fn an_operation_the_config_is_written_for_may_be_added() {
+    let mut config = twitch::config::Config::default();
+    config.operations.insert(
+        "ban-user".to_string(),
+        twitch::config::OperationOverride {
+            description: Some("Bans a user.".to_string()),
+            ..Default::default()
+        },
+    );
+    let new = fixture();
+    let mut old = serde_json::to_value(&new).unwrap();
+    old["paths"]["/moderation/bans"]
+        .as_object_mut()
+        .unwrap()
+        .remove("post");
+    let old = serde_json::from_value(old).unwrap();
+
+    // the older spec doesn't have the operation, so the override
+    // for it can't be checked.
+    assert!(twitch::build(&old, &config, &[]).is_err());
+    let old = twitch::build_unchecked(&old, &config, &[]).unwrap();
+    let new = twitch::build_unchecked(&new, &config, &[]).unwrap();
+
+    let found = twitch::diff::diff(&old, &new)
+        .into_iter()
+        .map(|change| (change.location, change.kind, change.breaking))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        change("moderation.banUser", ChangeKind::MethodAdded, false)
+    );
+}
@@ -0,0 +1,113 @@
+//! The include and exclude rules trim the client down to what is
+//! needed.
+
+use std::path::Path;
+use twitch::config::Config;
+use twitch::extract::Extract;
+use twitch::filter::{FilterRules, Filters};
+
+/// The methods left after applying the filters to the fixture, as
+/// `namespace.method`.
+fn methods(filters: &Filters) -> Result<Vec<String>, anyhow::Error> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let api = twitch::file::load(&twitch::file::LoadOptions {
+        path: Some(dir.join("tests/fixtures/openapi.json")),
+        patches: Some(dir.join("patches")),
+        frozen: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let config = Config::default();
+    let mut extract = Extract::new(&api, &config).unwrap();
+    filters.apply(&mut extract)?;
+    Ok(extract
+        .namespaces
+        .iter()
+        .flat_map(|(name, namespace)| {
+            namespace
+                .methods
+                .iter()
+                .map(move |method| format!("{name}.{}", method.name))
+        })
+        .collect())
+}
+
+fn rules(namespaces: &[&str], operations: &[&str], paths: &[&str]) -> FilterRules {
+    let strings = |items: &[&str]| items.iter().map(|item| item.to_string()).collect();
+    FilterRules {
+        namespaces: strings(namespaces),
+        operations: strings(operations),
+        paths: strings(paths),
+    }
+}
+
+fn include(rules: FilterRules) -> Filters {
+    Filters {
+        include: rules,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn without_rules_everything_is_generated() {
+    assert_eq!(
+        methods(&Filters::default()).unwrap(),
+        [
+            "users.getUsers",
+            "users.updateUser",
+            "users.updateUserExtensions",
+            "moderation.banUser",
+            "moderation.unbanUser",
+            "eventSub.getEventsubSubscriptions",
+            "clips.downloadClip",
+        ]
+    );
+}
+
+#[test]
+fn namespaces_match_by_either_name() {
+    assert_eq!(
+        methods(&include(rules(&["Moderation", "clips"], &[], &[]))).unwrap(),
+        [
+            "moderation.banUser",
+            "moderation.unbanUser",
+            "clips.downloadClip"
+        ]
+    );
+}
+
+#[test]
+fn operations_match_by_either_name() {
+    assert_eq!(
+        methods(&include(rules(&[], &["get-users", "downloadClip"], &[]))).unwrap(),
+        ["users.getUsers", "clips.downloadClip"]
+    );
+}
+
+#[test]
+fn a_path_glob_does_not_cross_a_slash() {
+    assert_eq!(
+        methods(&include(rules(&[], &[], &["/users/*"]))).unwrap(),
+        ["users.updateUserExtensions"]
+    );
+    assert_eq!(
+        methods(&include(rules(&[], &[], &["/**/bans"]))).unwrap(),
+        ["moderation.banUser", "moderation.unbanUser"]
+    );
+}
+
+#[test]
+fn exclude_wins_over_include() {
+    let filters = Filters {
+        include: rules(&["moderation", "users"], &[], &[]),
+        exclude: rules(&["users"], &["unban-user"], &[]),
+    };
+    assert_eq!(methods(&filters).unwrap(), ["moderation.banUser"]);
+}
+
+#[test]
+fn an_invalid_glob_is_an_error() {
+    let filters = include(rules(&[], &[], &["/users/[extensions"]));
+    assert!(filters.validate().is_err());
+    assert!(methods(&filters).is_err());
+}
@@ -0,0 +1,123 @@
+//! The spec may be JSON or YAML, and may come from stdin.
+
+use serde_json::json;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use twitch::file::{parse_document, LoadOptions};
+
+fn fixture() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/openapi.json")
+}
+
+/// The fixture, as YAML.
+fn yaml_fixture() -> String {
+    let api: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(fixture()).unwrap()).unwrap();
+    serde_yaml::to_string(&api).unwrap()
+}
+
+/// A directory of its own for the test to work in.
+fn workdir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn the_extension_decides_the_format() {
+    assert_eq!(
+        parse_document(b"openapi: 3.0.0", Some(Path::new("spec.yml"))).unwrap(),
+        json!({ "openapi": "3.0.0" })
+    );
+    assert_eq!(
+        parse_document(b"openapi: 3.0.0", Some(Path::new("spec.YAML"))).unwrap(),
+        json!({ "openapi": "3.0.0" })
+    );
+    // not JSON, though it would be fine as YAML.
+    assert!(parse_document(b"openapi: 3.0.0", Some(Path::new("spec.json"))).is_err());
+}
+
+#[test]
+fn without_an_extension_the_contents_decide_the_format() {
+    assert_eq!(
+        parse_document(b"\n  {\"openapi\": \"3.0.0\"}", None).unwrap(),
+        json!({ "openapi": "3.0.0" })
+    );
+    assert_eq!(
+        parse_document(b"openapi: 3.0.0\n", Some(Path::new("spec"))).unwrap(),
+        json!({ "openapi": "3.0.0" })
+    );
+    assert!(parse_document(b"{\"openapi\": ", None).is_err());
+}
+
+#[test]
+fn a_yaml_spec_loads_like_the_json_one() {
+    let dir = workdir("input-yaml");
+    std::fs::write(dir.join("openapi.yaml"), yaml_fixture()).unwrap();
+
+    let load = |path: PathBuf| {
+        twitch::file::load(&LoadOptions {
+            path: Some(path),
+            frozen: true,
+            ..Default::default()
+        })
+        .unwrap()
+    };
+    assert_eq!(load(dir.join("openapi.yaml")), load(fixture()));
+}
+
+/// Runs the binary with the arguments, with `stdin` piped into it,
+/// and a configuration that doesn't expect anything of the spec.
+fn run(dir: &Path, args: &[&str], stdin: &str) -> std::process::Output {
+    std::fs::write(dir.join("scopes.toml"), "").unwrap();
+    std::fs::write(
+        dir.join("twitch-gen.toml"),
+        "[spec]\nscopes = \"scopes.toml\"\npatches = \"patches\"\nlock = \"openapi.lock.json\"\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(dir.join("patches")).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_twitch"))
+        .args(args)
+        .arg("--config")
+        .arg(dir.join("twitch-gen.toml"))
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn a_spec_can_be_piped_in() {
+    let dir = workdir("input-stdin");
+    let output = run(&dir, &["lint", "-s", "-"], &yaml_fixture());
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // a piped spec is never checked against the lockfile.
+    assert!(!dir.join("openapi.lock.json").exists());
+}
+
+#[test]
+fn a_piped_spec_is_refused_in_frozen_mode() {
+    let dir = workdir("input-stdin-frozen");
+    let output = run(&dir, &["lint", "-s", "-", "--offline"], &yaml_fixture());
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("stdin"), "{stderr}");
+}
@@ -0,0 +1,175 @@
+//! Token parameters are classified by the rules in the configuration,
+//! and `lint` reports what would make for a poor client.
+
+use serde_json::Value;
+use std::path::Path;
+use twitch::config::Config;
+use twitch::extract::{Extract, ParamKind};
+use twitch::openapiv3::OpenAPI;
+
+/// The fixture spec, after `edit`.
+fn spec(edit: impl FnOnce(&mut Value)) -> OpenAPI {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let api = twitch::file::load(&twitch::file::LoadOptions {
+        path: Some(dir.join("tests/fixtures/openapi.json")),
+        patches: Some(dir.join("patches")),
+        frozen: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let mut api = serde_json::to_value(api).unwrap();
+    edit(&mut api);
+    serde_json::from_value(api).unwrap()
+}
+
+fn config(config: &str) -> Config { toml::from_str(config).unwrap() }
+
+/// How the parameter at the location was classified, and why.
+fn explain(api: &OpenAPI, config: &Config, location: &str) -> (ParamKind, String) {
+    let extract = Extract::new(api, config).unwrap();
+    twitch::lint::explain(&extract)
+        .into_iter()
+        .find(|explanation| explanation.location == location)
+        .map(|explanation| (explanation.kind, explanation.reason))
+        .unwrap_or_else(|| panic!("no parameter at {location}"))
+}
+
+fn findings(api: &OpenAPI) -> Vec<String> {
+    let config = Config::default();
+    let extract = Extract::new(api, &config).unwrap();
+    twitch::lint::lint(&extract)
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[test]
+fn the_default_rules_classify_the_fixture() {
+    let api = spec(|_| {});
+    let config = Config::default();
+
+    assert_eq!(
+        explain(&api, &config, "moderation.unbanUser(moderator_id)"),
+        (
+            ParamKind::QueryCurrentUserToken,
+            "by the rule `current-user`".to_string()
+        )
+    );
+    assert_eq!(
+        explain(&api, &config, "moderation.unbanUser(user_id)"),
+        (
+            ParamKind::QueryUserToken,
+            "by the rule `query-user-ids`".to_string()
+        )
+    );
+    assert_eq!(
+        explain(&api, &config, "users.getUsers(login)"),
+        (ParamKind::Query, "no rule matched".to_string())
+    );
+}
+
+#[test]
+fn a_rule_by_name_does_not_depend_on_the_description() {
+    let api = spec(|api| {
+        api["paths"]["/moderation/bans"]["delete"]["parameters"][1]["description"] =
+            "The ID of the moderator.".into();
+    });
+
+    // the default rule goes by the description...
+    assert_eq!(
+        explain(
+            &api,
+            &Config::default(),
+            "moderation.unbanUser(moderator_id)"
+        )
+        .0,
+        ParamKind::Query
+    );
+    // ...but one that goes by the name doesn't care.
+    let config = config(
+        r#"
+        [[token-params.rules]]
+        name = "moderators"
+        role = "current-user"
+        names = ["moderator_id"]
+        "#,
+    );
+    assert_eq!(
+        explain(&api, &config, "moderation.unbanUser(moderator_id)"),
+        (
+            ParamKind::QueryCurrentUserToken,
+            "by the rule `moderators`".to_string()
+        )
+    );
+}
+
+#[test]
+fn an_override_wins_over_the_rules() {
+    let api = spec(|_| {});
+    let config = config(
+        r#"
+        [operations.unban-user.token-params]
+        user_id = "plain"
+        "#,
+    );
+
+    assert_eq!(
+        explain(&api, &config, "moderation.unbanUser(user_id)"),
+        (
+            ParamKind::Query,
+            "by `operations.unban-user.token-params`".to_string()
+        )
+    );
+}
+
+#[test]
+fn a_role_that_does_not_fit_the_parameter_is_an_error() {
+    let api = spec(|_| {});
+    let config = config(
+        r#"
+        [operations.ban-user.token-params]
+        data = "current-user"
+        "#,
+    );
+
+    assert!(Extract::new(&api, &config).is_err());
+}
+
+#[test]
+fn the_fixture_is_clean() {
+    assert_eq!(findings(&spec(|_| {})), Vec::<String>::new());
+}
+
+#[test]
+fn missing_descriptions_are_reported() {
+    let api = spec(|api| {
+        let get = &mut api["paths"]["/users"]["get"];
+        get.as_object_mut().unwrap().remove("description");
+        get["parameters"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("description");
+    });
+
+    assert_eq!(
+        findings(&api),
+        [
+            "users.getUsers: method has no description",
+            "users.getUsers(id): parameter has no description",
+        ]
+    );
+}
+
+#[test]
+fn a_path_placeholder_without_a_parameter_is_reported() {
+    let api = spec(|api| {
+        let paths = api["paths"].as_object_mut().unwrap();
+        let download = paths.remove("/clips/download").unwrap();
+        paths.insert("/clips/{clip}/download".to_string(), download);
+    });
+
+    assert_eq!(
+        findings(&api),
+        ["clips.downloadClip: the path has a `{clip}`, but no path parameter for it"]
+    );
+}
@@ -0,0 +1,85 @@
+//! The lockfile pins both the hash of the spec and where it came
+//! from.
+
+use std::path::{Path, PathBuf};
+use twitch::file::{LoadOptions, LockUpdate, SpecLock};
+
+const SOURCE: &str = "https://example.com/openapi.json";
+
+/// A directory of its own for the test to work in, with a copy of
+/// the fixture spec in it.
+fn workdir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/openapi.json"),
+        dir.join("openapi.json"),
+    )
+    .unwrap();
+    dir
+}
+
+fn load(dir: &Path, source: &str, frozen: bool) -> Result<LockUpdate, anyhow::Error> {
+    twitch::file::load_locked(&LoadOptions {
+        path: Some(dir.join("openapi.json")),
+        source: Some(source.to_string()),
+        lock: Some(dir.join("openapi.lock.json")),
+        frozen,
+        ..Default::default()
+    })
+    .map(|(_, update)| update)
+}
+
+fn lock(dir: &Path) -> SpecLock {
+    serde_json::from_slice(&std::fs::read(dir.join("openapi.lock.json")).unwrap()).unwrap()
+}
+
+#[test]
+fn a_matching_lock_is_unchanged() {
+    let dir = workdir("lock-unchanged");
+    assert!(matches!(
+        load(&dir, SOURCE, false).unwrap(),
+        LockUpdate::Created { .. }
+    ));
+    assert_eq!(load(&dir, SOURCE, true).unwrap(), LockUpdate::Unchanged);
+    assert_eq!(lock(&dir).source, SOURCE);
+}
+
+#[test]
+fn a_different_source_is_refused_when_frozen() {
+    let dir = workdir("lock-source-frozen");
+    load(&dir, SOURCE, false).unwrap();
+    let before = lock(&dir);
+
+    let err = load(&dir, "https://example.org/openapi.json", true).unwrap_err();
+    assert!(err.to_string().contains("example.org"), "{err:#}");
+    assert_eq!(lock(&dir), before);
+}
+
+#[test]
+fn a_different_source_is_recorded_otherwise() {
+    let dir = workdir("lock-source-changed");
+    load(&dir, SOURCE, false).unwrap();
+    let before = lock(&dir);
+
+    assert_eq!(
+        load(&dir, "https://example.org/openapi.json", false).unwrap(),
+        LockUpdate::SourceChanged {
+            previous: SOURCE.to_string(),
+            source: "https://example.org/openapi.json".to_string(),
+        }
+    );
+    let after = lock(&dir);
+    assert_eq!(after.source, "https://example.org/openapi.json");
+    assert_eq!(after.sha256, before.sha256);
+}
+
+#[test]
+fn a_different_hash_is_refused_when_frozen() {
+    let dir = workdir("lock-hash-frozen");
+    load(&dir, SOURCE, false).unwrap();
+    std::fs::write(dir.join("openapi.json"), "{\"openapi\": \"3.0.0\"}").unwrap();
+
+    assert!(load(&dir, SOURCE, true).is_err());
+}
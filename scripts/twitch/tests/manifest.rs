@@ -0,0 +1,126 @@
+//! Generating only ever removes the files it generated itself.
+
+use std::path::{Path, PathBuf};
+use twitch::config::Config;
+use twitch::filter::{FilterRules, Filters};
+use twitch::manifest::{Manifest, MANIFEST_FILE};
+use twitch::render::StaleFile;
+
+const HAND_WRITTEN: &str = "// written by hand.\nexport const answer = 42;\n";
+
+/// A directory of its own for the test to work in.
+fn workdir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Generates the fixture into `dir`, without the given namespaces.
+fn generate(dir: &Path, exclude: &[&str], prune: bool) -> Vec<StaleFile> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let api = twitch::file::load(&twitch::file::LoadOptions {
+        path: Some(root.join("tests/fixtures/openapi.json")),
+        patches: Some(root.join("patches")),
+        frozen: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let config = Config::default();
+    let filters = Filters {
+        exclude: FilterRules {
+            namespaces: exclude.iter().map(|name| name.to_string()).collect(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let ir = twitch::build(&api, &config, &[&filters]).unwrap();
+    twitch::render::render(&ir, &config.render, dir, prune).unwrap()
+}
+
+fn stale(files: &[StaleFile]) -> Vec<(&str, bool)> {
+    files
+        .iter()
+        .map(|file| (file.name.as_str(), file.removed))
+        .collect()
+}
+
+fn manifest(dir: &Path) -> Vec<String> {
+    Manifest::read(dir)
+        .unwrap()
+        .unwrap()
+        .files
+        .into_iter()
+        .collect()
+}
+
+#[test]
+fn pruning_removes_stale_generated_files() {
+    let dir = workdir("manifest-prune");
+    assert!(stale(&generate(&dir, &[], true)).is_empty());
+    assert!(manifest(&dir).contains(&"clips.ts".to_string()));
+    std::fs::write(dir.join("handwritten.ts"), HAND_WRITTEN).unwrap();
+
+    assert_eq!(
+        stale(&generate(&dir, &["clips"], true)),
+        [("clips.ts", true)]
+    );
+    assert!(!dir.join("clips.ts").exists());
+    assert!(!manifest(&dir).contains(&"clips.ts".to_string()));
+    assert_eq!(
+        std::fs::read_to_string(dir.join("handwritten.ts")).unwrap(),
+        HAND_WRITTEN
+    );
+}
+
+#[test]
+fn pruning_keeps_a_listed_file_without_the_notice() {
+    let dir = workdir("manifest-taken-over");
+    generate(&dir, &[], true);
+    // someone took the file over, and dropped the notice.
+    std::fs::write(dir.join("clips.ts"), HAND_WRITTEN).unwrap();
+
+    assert_eq!(
+        stale(&generate(&dir, &["clips"], true)),
+        [("clips.ts", false)]
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("clips.ts")).unwrap(),
+        HAND_WRITTEN
+    );
+}
+
+#[test]
+fn without_pruning_stale_files_stay_in_the_manifest() {
+    let dir = workdir("manifest-no-prune");
+    generate(&dir, &[], true);
+
+    assert_eq!(
+        stale(&generate(&dir, &["clips"], false)),
+        [("clips.ts", false)]
+    );
+    assert!(dir.join("clips.ts").exists());
+    assert!(manifest(&dir).contains(&"clips.ts".to_string()));
+
+    // so that a later run can still remove it.
+    assert_eq!(
+        stale(&generate(&dir, &["clips"], true)),
+        [("clips.ts", true)]
+    );
+    assert!(!dir.join("clips.ts").exists());
+}
+
+#[test]
+fn without_a_manifest_only_files_with_the_notice_are_owned() {
+    let dir = workdir("manifest-missing");
+    generate(&dir, &[], true);
+    std::fs::remove_file(dir.join(MANIFEST_FILE)).unwrap();
+    std::fs::write(dir.join("handwritten.ts"), HAND_WRITTEN).unwrap();
+
+    assert_eq!(
+        stale(&generate(&dir, &["clips"], true)),
+        [("clips.ts", true)]
+    );
+    assert!(!dir.join("clips.ts").exists());
+    assert!(dir.join("handwritten.ts").exists());
+}
@@ -0,0 +1,110 @@
+//! The `nullable` overrides reach the fields they name, wherever the
+//! schema ends up.
+
+use std::path::Path;
+use twitch::config::Config;
+use twitch::ir::{Method, Type};
+
+fn build(config: &str) -> Result<twitch::ir::Api, anyhow::Error> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let api = twitch::file::load(&twitch::file::LoadOptions {
+        path: Some(dir.join("tests/fixtures/openapi.json")),
+        patches: Some(dir.join("patches")),
+        frozen: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let config: Config = toml::from_str(config).unwrap();
+    twitch::build(&api, &config, &[])
+}
+
+fn method<'a>(ir: &'a twitch::ir::Api, namespace: &str, name: &str) -> &'a Method {
+    ir.namespaces[namespace]
+        .methods
+        .iter()
+        .find(|method| method.name == name)
+        .unwrap()
+}
+
+/// The type of the field of the object.
+fn field<'a>(ty: &'a Type, name: &str) -> &'a Type {
+    let Type::Object(obj) = ty.non_null() else {
+        panic!("expected an object, but got {ty:?}");
+    };
+    &obj.properties
+        .iter()
+        .find(|prop| prop.name == name)
+        .unwrap()
+        .ty
+}
+
+fn items(ty: &Type) -> &Type {
+    let Type::Array { items } = ty.non_null() else {
+        panic!("expected an array, but got {ty:?}");
+    };
+    items
+}
+
+#[test]
+fn a_schema_override_applies_to_an_inlined_response() {
+    let ir = build(
+        r#"
+        [schemas.BanUserResponse]
+        nullable = ["data[].end_time"]
+        "#,
+    )
+    .unwrap();
+
+    let response = method(&ir, "moderation", "banUser")
+        .response
+        .as_ref()
+        .unwrap();
+    let end_time = field(items(field(response, "data")), "end_time");
+    assert!(end_time.is_nullable(), "{end_time:?}");
+}
+
+#[test]
+fn a_schema_override_applies_to_an_inlined_request_body() {
+    let ir = build(
+        r#"
+        [schemas.BanUserBody]
+        nullable = ["data.reason"]
+        "#,
+    )
+    .unwrap();
+
+    let data = &method(&ir, "moderation", "banUser")
+        .parameters
+        .iter()
+        .find(|param| param.name == "data")
+        .unwrap()
+        .ty;
+    let reason = field(data, "reason");
+    assert!(reason.is_nullable(), "{reason:?}");
+}
+
+#[test]
+fn a_schema_override_for_a_missing_field_of_an_inlined_body_is_an_error() {
+    let err = build(
+        r#"
+        [schemas.BanUserResponse]
+        nullable = ["data[].ends_at"]
+        "#,
+    )
+    .unwrap_err();
+    assert!(format!("{err:#}").contains("ends_at"), "{err:#}");
+}
+
+#[test]
+fn a_schema_override_applies_to_a_referenced_schema() {
+    let ir = build(
+        r#"
+        [schemas.User]
+        nullable = ["created_at"]
+        "#,
+    )
+    .unwrap();
+
+    let created_at = field(&ir.schemas["User"].ty, "created_at");
+    assert!(created_at.is_nullable(), "{created_at:?}");
+}
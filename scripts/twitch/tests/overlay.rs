@@ -0,0 +1,140 @@
+//! Overlays only ever add to the spec; anything they would change is
+//! a conflict.
+
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// A directory of overlays of its own for the test, with the given
+/// overlays in it, by file name.
+fn overlays(name: &str, files: &[(&str, Value)]) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, overlay) in files {
+        std::fs::write(dir.join(file), serde_json::to_vec_pretty(overlay).unwrap()).unwrap();
+    }
+    dir
+}
+
+fn fixture() -> Value {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/openapi.json");
+    serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+}
+
+/// Applies every overlay in the directory to the fixture, in order.
+fn apply(dir: &Path) -> Result<Value, anyhow::Error> {
+    let mut api = fixture();
+    for overlay in twitch::overlay::load_overlays(dir)? {
+        overlay.apply(&mut api)?;
+    }
+    Ok(api)
+}
+
+fn get_streams() -> Value {
+    json!({
+        "get": {
+            "operationId": "get-streams",
+            "tags": ["Streams"],
+            "responses": { "204": { "description": "Success." } }
+        }
+    })
+}
+
+#[test]
+fn an_overlay_adds_operations() {
+    let dir = overlays(
+        "overlay-adds",
+        &[(
+            "streams.json",
+            json!({ "paths": { "/streams": get_streams() } }),
+        )],
+    );
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let api = twitch::file::load(&twitch::file::LoadOptions {
+        path: Some(root.join("tests/fixtures/openapi.json")),
+        patches: Some(root.join("patches")),
+        overlays: Some(dir),
+        frozen: true,
+        ..Default::default()
+    })
+    .unwrap();
+
+    assert!(api.paths.paths.contains_key("/streams"));
+    assert!(api.paths.paths.contains_key("/users"));
+}
+
+#[test]
+fn an_identical_definition_is_not_a_conflict() {
+    let users = fixture()["paths"]["/users"].clone();
+    let dir = overlays(
+        "overlay-identical",
+        &[
+            ("a.json", json!({ "paths": { "/users": users } })),
+            ("b.json", json!({ "paths": { "/streams": get_streams() } })),
+            ("c.json", json!({ "paths": { "/streams": get_streams() } })),
+        ],
+    );
+
+    let api = apply(&dir).unwrap();
+    assert_eq!(api["paths"]["/users"], fixture()["paths"]["/users"]);
+}
+
+#[test]
+fn a_new_method_on_an_existing_path_is_not_a_conflict() {
+    let delete = json!({
+        "delete": { "operationId": "delete-clip", "tags": ["Clips"], "responses": {} }
+    });
+    let dir = overlays(
+        "overlay-new-method",
+        &[("a.json", json!({ "paths": { "/clips/download": delete } }))],
+    );
+
+    let api = apply(&dir).unwrap();
+    assert!(api["paths"]["/clips/download"]["get"].is_object());
+    assert!(api["paths"]["/clips/download"]["delete"].is_object());
+}
+
+#[test]
+fn every_conflict_is_reported() {
+    let dir = overlays(
+        "overlay-conflicts",
+        &[(
+            "a.json",
+            json!({
+                "paths": { "/users": { "get": { "operationId": "get-users-again" } } },
+                "components": { "schemas": { "User": { "type": "string" } } },
+                "tags": [{ "name": "Users", "description": "Something else." }]
+            }),
+        )],
+    );
+
+    let err = format!("{:#}", apply(&dir).unwrap_err());
+    assert!(err.contains("paths./users.get"), "{err}");
+    assert!(err.contains("components.schemas.User"), "{err}");
+    assert!(err.contains("tags.Users"), "{err}");
+}
+
+#[test]
+fn a_later_overlay_conflicts_with_an_earlier_one() {
+    let mut other = get_streams();
+    other["get"]["operationId"] = json!("get-other-streams");
+    let dir = overlays(
+        "overlay-earlier",
+        &[
+            ("a.json", json!({ "paths": { "/streams": get_streams() } })),
+            ("b.json", json!({ "paths": { "/streams": other } })),
+        ],
+    );
+
+    let err = format!("{:#}", apply(&dir).unwrap_err());
+    assert!(err.contains("b.json"), "{err}");
+    assert!(err.contains("paths./streams.get"), "{err}");
+}
+
+#[test]
+fn an_overlay_may_not_have_other_keys() {
+    let dir = overlays("overlay-keys", &[("a.json", json!({ "security": [] }))]);
+
+    let err = format!("{:#}", apply(&dir).unwrap_err());
+    assert!(err.contains("`security`"), "{err}");
+}
@@ -0,0 +1,79 @@
+//! Patches correct the spec before it is deserialized, and fail
+//! loudly once upstream changes under them.
+
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// A directory of patches of its own for the test, with the given
+/// patches in it, by file name.
+fn patches(name: &str, files: &[(&str, Value)]) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, patch) in files {
+        std::fs::write(dir.join(file), serde_json::to_vec_pretty(patch).unwrap()).unwrap();
+    }
+    dir
+}
+
+fn load(patches: PathBuf) -> Result<Value, anyhow::Error> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let api = twitch::file::load(&twitch::file::LoadOptions {
+        path: Some(root.join("tests/fixtures/openapi.json")),
+        patches: Some(patches),
+        frozen: true,
+        ..Default::default()
+    })?;
+    Ok(serde_json::to_value(api).unwrap())
+}
+
+#[test]
+fn the_repository_patches_apply_to_the_fixture() {
+    let api = load(Path::new(env!("CARGO_MANIFEST_DIR")).join("patches")).unwrap();
+    assert_eq!(
+        api["components"]["schemas"]["UpdateUserExtensionsBody"]["properties"]["data"]["type"],
+        "object"
+    );
+}
+
+#[test]
+fn a_patch_that_no_longer_applies_is_an_error() {
+    let dir = patches(
+        "patches-stale",
+        &[(
+            "0001-stale.json",
+            json!([
+                { "op": "test", "path": "/paths/~1users/get/operationId", "value": "get-user" },
+                { "op": "replace", "path": "/paths/~1users/get/operationId", "value": "users" }
+            ]),
+        )],
+    );
+
+    let err = format!("{:#}", load(dir).unwrap_err());
+    assert!(err.contains("0001-stale.json"), "{err}");
+    assert!(err.contains("needs to be updated or removed"), "{err}");
+}
+
+#[test]
+fn patches_apply_in_file_name_order() {
+    let dir = patches(
+        "patches-order",
+        &[
+            (
+                "0002-describe.json",
+                json!([
+                    { "op": "test", "path": "/paths/~1users/get/summary", "value": "Get users" },
+                    { "op": "replace", "path": "/paths/~1users/get/summary", "value": "Users" }
+                ]),
+            ),
+            (
+                "0001-summarize.merge.json",
+                json!({ "paths": { "/users": { "get": { "summary": "Get users" } } } }),
+            ),
+        ],
+    );
+
+    let api = load(dir).unwrap();
+    assert_eq!(api["paths"]["/users"]["get"]["summary"], "Users");
+    assert_eq!(api["paths"]["/users"]["get"]["operationId"], "get-users");
+}
@@ -0,0 +1,178 @@
+//! The scopes file corrects what the spec says each operation
+//! requires, and the generated methods check for them.
+
+use std::path::{Path, PathBuf};
+use twitch::config::Config;
+use twitch::ir::Api;
+use twitch::scopes::ScopeOverrides;
+
+fn build(scopes: &str) -> Api {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let api = twitch::file::load(&twitch::file::LoadOptions {
+        path: Some(dir.join("tests/fixtures/openapi.json")),
+        patches: Some(dir.join("patches")),
+        frozen: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let config = Config {
+        scopes: toml::from_str(scopes).unwrap(),
+        ..Config::default()
+    };
+    twitch::build(&api, &config, &[]).unwrap()
+}
+
+/// The sets of scopes the method requires.
+fn scopes(ir: &Api, namespace: &str, name: &str) -> Vec<Vec<String>> {
+    ir.namespaces[namespace]
+        .methods
+        .iter()
+        .find(|method| method.name == name)
+        .unwrap()
+        .requires_scopes
+        .clone()
+}
+
+fn sets(sets: &[&[&str]]) -> Vec<Vec<String>> {
+    sets.iter()
+        .map(|set| set.iter().map(|scope| scope.to_string()).collect())
+        .collect()
+}
+
+#[test]
+fn without_corrections_the_spec_decides() {
+    let ir = build("");
+    assert_eq!(
+        scopes(&ir, "moderation", "banUser"),
+        sets(&[&["moderator:manage:banned_users"]])
+    );
+    assert_eq!(scopes(&ir, "moderation", "unbanUser"), sets(&[]));
+}
+
+#[test]
+fn any_of_replaces_what_the_spec_says() {
+    let ir = build(
+        r#"
+        [get-users]
+        any-of = []
+
+        [unban-user]
+        any-of = [["moderator:manage:banned_users"]]
+        "#,
+    );
+    assert_eq!(scopes(&ir, "users", "getUsers"), sets(&[]));
+    assert_eq!(
+        scopes(&ir, "moderation", "unbanUser"),
+        sets(&[&["moderator:manage:banned_users"]])
+    );
+}
+
+#[test]
+fn also_adds_to_what_the_spec_says() {
+    let ir = build(
+        r#"
+        [ban-user]
+        also = [["moderator:manage:banned_users"], ["moderator:manage:chat_messages", "user:edit"]]
+        "#,
+    );
+    assert_eq!(
+        scopes(&ir, "moderation", "banUser"),
+        sets(&[
+            &["moderator:manage:banned_users"],
+            &["moderator:manage:chat_messages", "user:edit"]
+        ])
+    );
+    // a scope the spec doesn't declare still makes it into the
+    // catalog, without a description.
+    assert_eq!(ir.scopes["moderator:manage:chat_messages"], None);
+    assert_eq!(
+        ir.scopes["user:edit"].as_deref(),
+        Some("Manage a user object.")
+    );
+}
+
+#[test]
+fn the_generated_method_checks_its_scopes() {
+    let ir = build("");
+    let files = twitch::render::render_files(&ir, &Config::default().render).unwrap();
+    let moderation = String::from_utf8_lossy(&files["moderation.ts"]);
+    assert!(
+        moderation.contains("const requiredScopes = [[\"moderator:manage:banned_users\"]];"),
+        "{moderation}"
+    );
+}
+
+#[test]
+fn an_invalid_scope_is_an_error() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("scopes-invalid");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("scopes.toml"),
+        "[ban-user]\nalso = [[\"moderator:manage banned_users\"]]\n",
+    )
+    .unwrap();
+
+    let err = format!(
+        "{:#}",
+        ScopeOverrides::load(&dir.join("scopes.toml")).unwrap_err()
+    );
+    assert!(err.contains("ban-user"), "{err}");
+}
+
+#[test]
+fn the_catalog_has_every_scope_and_method() {
+    let ir = build(
+        r#"
+        [unban-user]
+        any-of = [["moderator:manage:banned_users"], ["moderator:manage:chat_messages"]]
+        "#,
+    );
+    let files = twitch::render::render_files(&ir, &Config::default().render).unwrap();
+    let scopes = String::from_utf8_lossy(&files["scopes.ts"]);
+
+    assert!(
+        scopes.contains("  \"moderator:manage:banned_users\": \"Ban and unban users.\",\n"),
+        "{scopes}"
+    );
+    assert!(
+        scopes.contains("  \"moderator:manage:chat_messages\": \"\",\n"),
+        "{scopes}"
+    );
+    assert!(
+        scopes.contains(
+            "  \"moderation.unbanUser\": [[\"moderator:manage:banned_users\"], \
+             [\"moderator:manage:chat_messages\"]],\n"
+        ),
+        "{scopes}"
+    );
+    assert!(
+        scopes.contains("  \"clips.downloadClip\": [],\n"),
+        "{scopes}"
+    );
+    // filtered out by the default configuration.
+    assert!(!scopes.contains("eventSub."), "{scopes}");
+}
+
+#[test]
+fn minimal_scopes_uses_the_type_prefix() {
+    let ir = build("");
+    let mut render = Config::default().render;
+    render.type_prefix = "Helix".to_string();
+    let files = twitch::render::render_files(&ir, &render).unwrap();
+    let scopes = String::from_utf8_lossy(&files["scopes.ts"]);
+
+    assert!(
+        scopes.contains(
+            "export function minimalScopes(methods: Iterable<HelixMethod>): HelixScope[] {"
+        ),
+        "{scopes}"
+    );
+    assert!(scopes.contains("= HelixMethodScopes[method];"), "{scopes}");
+    assert!(
+        scopes.contains("Object.keys(HelixScopes) as HelixScope[]"),
+        "{scopes}"
+    );
+    assert!(!scopes.contains('$'), "{scopes}");
+    assert!(!scopes.contains("Twitch"), "{scopes}");
+}
@@ -0,0 +1,149 @@
+//! `update-spec` only downloads the spec when upstream has changed.
+//!
+//! These run against a stand-in for upstream on a local port, which
+//! answers with the responses it is given, in order, and hands back
+//! the headers of every request it gets.
+
+use std::io::{BufRead as _, BufReader, Write as _};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use twitch::file::{SpecUpdate, UpdateOptions};
+
+const LAST_MODIFIED: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+
+/// Serves `responses`, one per connection, and returns the URL to
+/// request, along with the headers of the requests it gets (with
+/// their names in lowercase).
+fn serve(responses: Vec<String>) -> (String, mpsc::Receiver<Vec<(String, String)>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/openapi.json", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut headers = Vec::new();
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            loop {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+                let Some((name, value)) = line.trim_end().split_once(':') else {
+                    break;
+                };
+                headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+            }
+            sender.send(headers).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    (url, receiver)
+}
+
+fn ok(body: &str, etag: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\nETag: {etag}\r\nLast-Modified: {LAST_MODIFIED}\r\nContent-Length: \
+         {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+fn not_modified() -> String { "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string() }
+
+fn header<'h>(headers: &'h [(String, String)], name: &str) -> Option<&'h str> {
+    headers
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// A directory of its own for the test to work in.
+fn workdir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn options(source: String, dir: &std::path::Path) -> UpdateOptions {
+    UpdateOptions {
+        source,
+        cache: dir.join("cache"),
+        path: dir.join("openapi.json"),
+        lock: Some(dir.join("openapi.lock.json")),
+    }
+}
+
+#[test]
+fn conditional_requests_round_trip_the_cache_headers() {
+    let dir = workdir("update-round-trip");
+    let (url, requests) = serve(vec![
+        ok("{\"openapi\": \"3.0.0\"}", "\"v1\""),
+        not_modified(),
+        ok("{\"openapi\": \"3.0.1\"}", "\"v2\""),
+    ]);
+    let options = options(url, &dir);
+
+    // nothing is cached yet, so the request is unconditional.
+    assert_eq!(twitch::file::update(&options).unwrap(), SpecUpdate::Changed);
+    let headers = requests.recv().unwrap();
+    assert_eq!(header(&headers, "if-none-match"), None);
+    assert_eq!(header(&headers, "if-modified-since"), None);
+    assert_eq!(
+        std::fs::read_to_string(&options.path).unwrap(),
+        "{\"openapi\": \"3.0.0\"}"
+    );
+    let lock = std::fs::read_to_string(dir.join("openapi.lock.json")).unwrap();
+
+    // the second request sends back what the first response said,
+    // and upstream has nothing new.
+    assert_eq!(
+        twitch::file::update(&options).unwrap(),
+        SpecUpdate::Unchanged
+    );
+    let headers = requests.recv().unwrap();
+    assert_eq!(header(&headers, "if-none-match"), Some("\"v1\""));
+    assert_eq!(header(&headers, "if-modified-since"), Some(LAST_MODIFIED));
+    assert_eq!(
+        std::fs::read_to_string(&options.path).unwrap(),
+        "{\"openapi\": \"3.0.0\"}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("openapi.lock.json")).unwrap(),
+        lock
+    );
+
+    // upstream changes; the new spec replaces the old, and its ETag
+    // is the one sent next time.
+    assert_eq!(twitch::file::update(&options).unwrap(), SpecUpdate::Changed);
+    let headers = requests.recv().unwrap();
+    assert_eq!(header(&headers, "if-none-match"), Some("\"v1\""));
+    assert_eq!(
+        std::fs::read_to_string(&options.path).unwrap(),
+        "{\"openapi\": \"3.0.1\"}"
+    );
+    assert_ne!(
+        std::fs::read_to_string(dir.join("openapi.lock.json")).unwrap(),
+        lock
+    );
+    let meta = std::fs::read_to_string(dir.join("cache/openapi.meta.json")).unwrap();
+    assert!(meta.contains("\\\"v2\\\""), "{meta}");
+}
+
+#[test]
+fn an_unchanged_body_is_unchanged_without_a_304() {
+    let dir = workdir("update-same-body");
+    // an upstream that ignores the conditional headers.
+    let (url, _requests) = serve(vec![
+        ok("{\"openapi\": \"3.0.0\"}", "\"v1\""),
+        ok("{\"openapi\": \"3.0.0\"}", "\"v1\""),
+    ]);
+    let options = options(url, &dir);
+
+    assert_eq!(twitch::file::update(&options).unwrap(), SpecUpdate::Changed);
+    assert_eq!(
+        twitch::file::update(&options).unwrap(),
+        SpecUpdate::Unchanged
+    );
+}